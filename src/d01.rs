@@ -1,49 +1,174 @@
-use std::collections::HashSet;
+use crate::params::{parses, Param, Params};
+
+pub const PARAMS: &[Param] = &[
+  Param {
+    name: "target",
+    default: "2020",
+    doc: "The sum the expenses should add up to.",
+    check: parses::<i64>,
+  },
+  Param {
+    name: "k",
+    default: "2",
+    doc: "Number of expenses to pick.",
+    check: parses::<usize>,
+  },
+];
+
+pub const PARAMS2: &[Param] = &[
+  PARAMS[0],
+  Param {
+    name: "k",
+    default: "3",
+    doc: PARAMS[1].doc,
+    check: PARAMS[1].check,
+  },
+];
 
 pub fn solve(input: &str) -> Option<Box<i64>> {
-  let numbers = parse(input);
-
-  if let Some((x, y)) = find_complement(&numbers, 0) {
-    return Some(Box::new(x * y));
-  }
-
-  None
+  solve_with(input, &Params::defaults(PARAMS))
 }
 
 pub fn solve2(input: &str) -> Option<Box<i64>> {
+  solve2_with(input, &Params::defaults(PARAMS2))
+}
+
+pub fn solve_with(input: &str, params: &Params) -> Option<Box<i64>> {
   let numbers = parse(input);
 
-  for z in numbers.iter() {
-    if let Some((x, y)) = find_complement(&numbers, *z) {
-      return Some(Box::new(x * y * z));
-    }
-  }
+  k_sum(&numbers, params.get("k"), params.get("target"), Search::First)
+    .first()
+    .map(|combination| Box::new(combination.iter().product()))
+}
+
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<i64>> {
+  solve_with(input, params)
+}
 
-  None
+/// Returns all distinct combinations of `k` expenses that sum to `target`.
+#[allow(clippy::box_collection)] // solvers return `Option<Box<T>>`
+pub fn combinations(input: &str, params: &Params) -> Option<Box<Vec<Vec<i64>>>> {
+  let numbers = parse(input);
+  Some(Box::new(k_sum(&numbers, params.get("k"), params.get("target"), Search::All)))
 }
 
 // Helpers
 
-fn parse(input: &str) -> HashSet<i64> {
+fn parse(input: &str) -> Vec<i64> {
   input
     .trim_end()
     .split("\n")
     .map(|number| number.parse::<i64>().unwrap())
-    .collect::<HashSet<_>>()
+    .collect::<Vec<_>>()
 }
 
-// Returns a pair of numbers `x` and `y`, that together with `z`
-// sum to `2020`.
-// If nothing is found, returns `None`.
-fn find_complement(numbers: &HashSet<i64>, z: i64) -> Option<(i64, i64)> {
-  for x in numbers.iter() {
-    let y = 2020 - x - z;
-    if numbers.contains(&y) {
-      return Some((*x, y));
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Search {
+  First,
+  All,
+}
+
+/// Returns combinations of `k` numbers that sum to `target`, each combination sorted in ascending order.
+///
+/// `numbers` is treated as a multiset: each number can be used as many times as it occurs.
+/// Combinations are distinct by value, and `Search::First` stops after finding one.
+///
+/// Runs in `O(n log n)` for `k <= 2` and `O(n^(k - 1))` otherwise.
+fn k_sum(numbers: &[i64], k: usize, target: i64, search: Search) -> Vec<Vec<i64>> {
+  let mut sorted = numbers.to_vec();
+  sorted.sort_unstable();
+
+  let mut found = vec![];
+  if k > 0 {
+    k_sum_inner(&sorted, k, target, search, &mut vec![], &mut found);
   }
 
-  None
+  found
+}
+
+/// Searches `sorted` for `k` numbers summing to `target`, appending them to `prefix`.
+/// Returns `true` if the search should stop.
+fn k_sum_inner(
+  sorted: &[i64],
+  k: usize,
+  target: i64,
+  search: Search,
+  prefix: &mut Vec<i64>,
+  found: &mut Vec<Vec<i64>>,
+) -> bool {
+  let mut record = |combination: &[i64]| {
+    let mut full = prefix.clone();
+    full.extend_from_slice(combination);
+    found.push(full);
+
+    search == Search::First
+  };
+
+  match k {
+    1 => sorted.binary_search(&target).is_ok() && record(&[target]),
+    2 => {
+      if sorted.len() < 2 {
+        return false;
+      }
+
+      let (mut low, mut high) = (0, sorted.len() - 1);
+      while low < high {
+        let sum = sorted[low] + sorted[high];
+
+        if sum < target {
+          low += 1;
+        } else if sum > target {
+          high -= 1;
+        } else {
+          if record(&[sorted[low], sorted[high]]) {
+            return true;
+          }
+
+          // skip duplicates to keep combinations distinct
+          let (x, y) = (sorted[low], sorted[high]);
+          while low < high && sorted[low] == x {
+            low += 1;
+          }
+          while low < high && sorted[high] == y {
+            high -= 1;
+          }
+        }
+      }
+
+      false
+    }
+    _ => {
+      let largest = match sorted.last() {
+        Some(largest) => *largest,
+        None => return false,
+      };
+
+      for idx in 0..sorted.len() {
+        let x = sorted[idx];
+        if idx > 0 && sorted[idx - 1] == x {
+          continue;
+        }
+
+        // all remaining numbers are at least `x`, so the sum is at least `k * x`
+        if x * k as i64 > target {
+          break;
+        }
+        if x + largest * (k as i64 - 1) < target {
+          continue;
+        }
+
+        prefix.push(x);
+        let stop = k_sum_inner(&sorted[idx + 1..], k - 1, target - x, search, prefix, found);
+        prefix.pop();
+
+        if stop {
+          return true;
+        }
+      }
+
+      false
+    }
+  }
 }
 
 #[cfg(test)]
@@ -68,6 +193,30 @@ mod test {
     assert_eq!(solve2(SAMPLE_INPUT), Some(Box::new(241861950)));
   }
 
+  #[test]
+  fn k_sum_treats_input_as_multiset() {
+    assert_eq!(k_sum(&[1010, 5], 2, 2020, Search::First), Vec::<Vec<i64>>::new());
+    assert_eq!(k_sum(&[1010, 5, 1010], 2, 2020, Search::First), vec![vec![1010, 1010]]);
+    assert_eq!(k_sum(&[3, 3, 4], 3, 9, Search::All), Vec::<Vec<i64>>::new());
+    assert_eq!(k_sum(&[3, 3, 3], 3, 9, Search::All), vec![vec![3, 3, 3]]);
+  }
+
+  #[test]
+  fn k_sum_finds_all_distinct_combinations() {
+    let numbers = [1, 2, 3, 4, 5, 2, 3, -1, 6];
+
+    assert_eq!(
+      k_sum(&numbers, 2, 6, Search::All),
+      vec![vec![1, 5], vec![2, 4], vec![3, 3]]
+    );
+    assert_eq!(
+      k_sum(&numbers, 3, 6, Search::All),
+      vec![vec![-1, 1, 6], vec![-1, 2, 5], vec![-1, 3, 4], vec![1, 2, 3]]
+    );
+    assert_eq!(k_sum(&numbers, 1, 4, Search::All), vec![vec![4]]);
+    assert_eq!(k_sum(&numbers, 4, 1000, Search::All), Vec::<Vec<i64>>::new());
+  }
+
   #[test]
   fn part_one_works() {
     let input = fs::read_to_string("inputs/d01").unwrap();
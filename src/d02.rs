@@ -1,17 +1,86 @@
 use regex::Regex;
 
-pub fn solve(input: &str) -> Option<Box<usize>> {
-  let passwords = parse(input);
-  let valid_count = passwords.into_iter().filter(|password| password.is_valid()).count();
+use crate::json::Json;
+use crate::params::{parses, Param, Params};
+use crate::report::{Format, Report};
+
+pub const PARAMS: &[Param] = &[Param {
+  name: "policies",
+  default: "count;xor;and",
+  doc: "Policies to check, separated by ';': count, xor, and, regex:<pattern>, classes:<lower,upper,digit,...>.",
+  check: |policies| parse_policies(policies).map(|_| ()),
+}];
 
-  Some(Box::new(valid_count))
+pub const REPORT_PARAMS: &[Param] = &[
+  Param {
+    name: "policies",
+    default: "count;xor",
+    doc: PARAMS[0].doc,
+    check: PARAMS[0].check,
+  },
+  Param {
+    name: "format",
+    default: "text",
+    doc: "Report format, text or json.",
+    check: parses::<Format>,
+  },
+];
+
+pub fn solve(input: &str) -> Option<Box<usize>> {
+  Some(Box::new(count_valid(&parse(input), &CountRange)))
 }
 
 pub fn solve2(input: &str) -> Option<Box<usize>> {
+  Some(Box::new(count_valid(&parse(input), &PositionalXor)))
+}
+
+/// Returns the number of valid passwords for each of the `policies`.
+#[allow(clippy::box_collection)] // solvers return `Option<Box<T>>`
+pub fn solve_policies(input: &str, params: &Params) -> Option<Box<Vec<(String, usize)>>> {
+  let policies = match parse_policies(params.get_str("policies")) {
+    Ok(policies) => policies,
+    Err(error) => {
+      error!("{}", error);
+      return None;
+    }
+  };
   let passwords = parse(input);
-  let valid_count = passwords.into_iter().filter(|password| password.is_valid2()).count();
 
-  Some(Box::new(valid_count))
+  Some(Box::new(
+    policies
+      .iter()
+      .map(|policy| (policy.name(), count_valid(&passwords, policy.as_ref())))
+      .collect(),
+  ))
+}
+
+/// Lists the failed policies of each password, and the lines that could not be parsed.
+pub fn report(input: &str, params: &Params) -> Option<Box<Report>> {
+  let policies = match parse_policies(params.get_str("policies")) {
+    Ok(policies) => policies,
+    Err(error) => {
+      error!("{}", error);
+      return None;
+    }
+  };
+  let database = parse_database(input);
+
+  Some(Box::new(Report(match params.get::<Format>("format") {
+    Format::Text => database_to_text(&database, &policies),
+    Format::Json => database_to_json(&database, &policies).to_string(),
+  })))
+}
+
+fn count_valid(passwords: &[Password], policy: &dyn PasswordPolicy) -> usize {
+  passwords.iter().filter(|password| policy.is_valid(password)).count()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Database {
+  /// Passwords together with their line numbers.
+  passwords: Vec<(usize, Password)>,
+  /// Line numbers and problems of lines that could not be parsed.
+  diagnostics: Vec<(usize, String)>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -20,72 +89,372 @@ struct Password {
   password: String,
 }
 
-impl Password {
-  pub fn is_valid(&self) -> bool {
-    let policy_letter_count = self
-      .password
-      .chars()
-      .filter(|letter| *letter == self.policy.letter)
-      .count();
+/// The `min-max letter` rule written in front of each password.
+#[derive(Debug, PartialEq, Eq)]
+struct Policy {
+  letter: char,
+  min: usize,
+  max: usize,
+}
+
+/// A way to decide whether a password is valid, possibly using the rule stored next to it.
+trait PasswordPolicy {
+  fn name(&self) -> String;
+
+  /// Returns why `password` violates the policy.
+  fn check(&self, password: &Password) -> Result<(), String>;
+
+  fn is_valid(&self, password: &Password) -> bool {
+    self.check(password).is_ok()
+  }
+}
+
+/// The letter occurs between `min` and `max` times (inclusive).
+struct CountRange;
+
+impl PasswordPolicy for CountRange {
+  fn name(&self) -> String {
+    "count".to_string()
+  }
+
+  fn check(&self, password: &Password) -> Result<(), String> {
+    let Policy { letter, min, max } = password.policy;
+    let letter_count = password.password.chars().filter(|ch| *ch == letter).count();
+
+    if letter_count < min {
+      Err(format!("{:?} occurs {} times, expected at least {}", letter, letter_count, min))
+    } else if letter_count > max {
+      Err(format!("{:?} occurs {} times, expected at most {}", letter, letter_count, max))
+    } else {
+      Ok(())
+    }
+  }
+}
+
+/// Returns whether the letter is at the 1-based positions `min` and `max`,
+/// failing if any of them is outside of the password.
+fn positions_match(password: &Password) -> Result<(bool, bool), String> {
+  let password_chars = password.password.chars().collect::<Vec<_>>();
+  let letter_at = |position: usize| {
+    position
+      .checked_sub(1)
+      .and_then(|idx| password_chars.get(idx))
+      .map(|letter| *letter == password.policy.letter)
+      .ok_or_else(|| format!("position {} is outside of the password", position))
+  };
+
+  Ok((letter_at(password.policy.min)?, letter_at(password.policy.max)?))
+}
+
+/// The letter is at exactly one of the positions `min` and `max`.
+struct PositionalXor;
 
-    policy_letter_count >= self.policy.min && policy_letter_count <= self.policy.max
+impl PasswordPolicy for PositionalXor {
+  fn name(&self) -> String {
+    "xor".to_string()
   }
 
-  pub fn is_valid2(&self) -> bool {
-    let password_chars = self.password.chars().collect::<Vec<_>>();
-    if let (Some(first), Some(second)) = (
-      password_chars.get(self.policy.min - 1),
-      password_chars.get(self.policy.max - 1),
-    ) {
-      let first_matches = *first == self.policy.letter;
-      let second_matches = *second == self.policy.letter;
+  fn check(&self, password: &Password) -> Result<(), String> {
+    let Policy { letter, min, max } = password.policy;
 
-      // a xor b = (a and not b) or (not a and b)
-      return (first_matches && !second_matches) || (!first_matches && second_matches);
+    match positions_match(password)? {
+      (true, true) => Err(format!("both positions {} and {} contain {:?}", min, max, letter)),
+      (false, false) => Err(format!("neither position {} nor {} contains {:?}", min, max, letter)),
+      _ => Ok(()),
     }
+  }
+}
+
+/// The letter is at both of the positions `min` and `max`.
+struct PositionalAnd;
+
+impl PasswordPolicy for PositionalAnd {
+  fn name(&self) -> String {
+    "and".to_string()
+  }
+
+  fn check(&self, password: &Password) -> Result<(), String> {
+    let Policy { letter, min, max } = password.policy;
 
-    false
+    match positions_match(password)? {
+      (true, true) => Ok(()),
+      (first, _) => Err(format!(
+        "position {} does not contain {:?}",
+        if first { max } else { min },
+        letter
+      )),
+    }
   }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct Policy {
-  letter: char,
-  min: usize,
-  max: usize,
+/// The password matches a regex, ignoring the rule.
+struct Matches(Regex);
+
+impl PasswordPolicy for Matches {
+  fn name(&self) -> String {
+    format!("regex:{}", self.0)
+  }
+
+  fn check(&self, password: &Password) -> Result<(), String> {
+    if self.0.is_match(&password.password) {
+      Ok(())
+    } else {
+      Err(format!("does not match {}", self.0))
+    }
+  }
 }
 
-fn parse(input: &str) -> Vec<Password> {
-  let re = Regex::new(r"^(?P<min>\d+)-(?P<max>\d+) (?P<letter>\w): (?P<password>\w+)\n?$").unwrap();
-
-  input
-    .trim_end()
-    .split('\n')
-    .map(|line| match re.captures_iter(line).next() {
-      Some(cap) => {
-        match (
-          cap.name("min"),
-          cap.name("max"),
-          cap.name("letter"),
-          cap.name("password"),
-        ) {
-          (Some(min), Some(max), Some(letter), Some(password)) => {
-            let min = min.as_str().parse::<usize>().unwrap();
-            let max = max.as_str().parse::<usize>().unwrap();
-            let letter = letter.as_str().parse::<char>().unwrap();
-            let password = password.as_str().to_string();
-
-            Password {
-              policy: Policy { min, max, letter },
-              password,
-            }
-          }
-          _ => panic!("Invalid password format: {:?}", cap),
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+  Lower,
+  Upper,
+  Digit,
+  Alpha,
+  Punct,
+}
+
+impl CharClass {
+  const NAMES: [(&'static str, CharClass); 5] = [
+    ("lower", CharClass::Lower),
+    ("upper", CharClass::Upper),
+    ("digit", CharClass::Digit),
+    ("alpha", CharClass::Alpha),
+    ("punct", CharClass::Punct),
+  ];
+
+  fn parse(name: &str) -> Result<CharClass, String> {
+    CharClass::NAMES
+      .iter()
+      .find(|(class_name, _)| *class_name == name)
+      .map(|(_, class)| *class)
+      .ok_or_else(|| {
+        let names = CharClass::NAMES.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        format!("Unknown character class {:?}, expected one of: {}.", name, names.join(", "))
+      })
+  }
+
+  fn name(&self) -> &'static str {
+    CharClass::NAMES.iter().find(|(_, class)| class == self).unwrap().0
+  }
+
+  fn contains(&self, ch: char) -> bool {
+    match self {
+      CharClass::Lower => ch.is_lowercase(),
+      CharClass::Upper => ch.is_uppercase(),
+      CharClass::Digit => ch.is_ascii_digit(),
+      CharClass::Alpha => ch.is_alphabetic(),
+      CharClass::Punct => ch.is_ascii_punctuation(),
+    }
+  }
+}
+
+/// The password contains at least one character of each class, ignoring the rule.
+struct RequiredClasses(Vec<CharClass>);
+
+impl PasswordPolicy for RequiredClasses {
+  fn name(&self) -> String {
+    let names = self.0.iter().map(|class| class.name()).collect::<Vec<_>>();
+    format!("classes:{}", names.join(","))
+  }
+
+  fn check(&self, password: &Password) -> Result<(), String> {
+    let missing = self
+      .0
+      .iter()
+      .filter(|class| !password.password.chars().any(|ch| class.contains(ch)))
+      .map(|class| class.name())
+      .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+      Ok(())
+    } else {
+      Err(format!("no {} characters", missing.join(", ")))
+    }
+  }
+}
+
+/// Parses a policy spec: policies separated by `;` or new lines, each being
+/// `count`, `xor`, `and`, `regex:<pattern>` or `classes:<class>,...`.
+fn parse_policies(spec: &str) -> Result<Vec<Box<dyn PasswordPolicy>>, String> {
+  spec
+    .split([';', '\n'])
+    .map(|entry| entry.trim())
+    .filter(|entry| !entry.is_empty())
+    .map(|entry| {
+      let (kind, args) = entry.split_once(':').unwrap_or((entry, ""));
+
+      let policy: Box<dyn PasswordPolicy> = match kind {
+        "count" => Box::new(CountRange),
+        "xor" => Box::new(PositionalXor),
+        "and" => Box::new(PositionalAnd),
+        "regex" => Box::new(Matches(
+          Regex::new(args).map_err(|error| format!("Invalid regex {:?}: {}", args, error))?,
+        )),
+        "classes" => Box::new(RequiredClasses(
+          args
+            .split(',')
+            .map(|name| CharClass::parse(name.trim()))
+            .collect::<Result<Vec<_>, _>>()?,
+        )),
+        _ => {
+          return Err(format!(
+            "Unknown policy {:?}, expected one of: count, xor, and, regex:<pattern>, classes:<class>,...",
+            kind
+          ))
         }
+      };
+
+      Ok(policy)
+    })
+    .collect()
+}
+
+/// Parses the passwords, logging lines that could not be parsed.
+fn parse(input: &str) -> Vec<Password> {
+  let database = parse_database(input);
+  for (line_number, problem) in database.diagnostics.iter() {
+    warn!("Skipping line {}: {}", line_number, problem);
+  }
+
+  database.passwords.into_iter().map(|(_, password)| password).collect()
+}
+
+/// Parses the passwords, collecting malformed lines as diagnostics. Blank lines are skipped.
+fn parse_database(input: &str) -> Database {
+  lazy_static! {
+    static ref RE: Regex = Regex::new(r"^(?P<min>\d+)-(?P<max>\d+) (?P<letter>\w): (?P<password>\S+)$").unwrap();
+  }
+
+  let mut database = Database {
+    passwords: vec![],
+    diagnostics: vec![],
+  };
+
+  for (idx, line) in input.lines().enumerate() {
+    if line.trim().is_empty() {
+      continue;
+    }
+
+    let parsed = match RE.captures(line) {
+      Some(cap) => match (cap["min"].parse::<usize>(), cap["max"].parse::<usize>()) {
+        (Ok(min), Ok(max)) if min <= max => Ok(Password {
+          policy: Policy {
+            letter: cap["letter"].chars().next().unwrap(),
+            min,
+            max,
+          },
+          password: cap["password"].to_string(),
+        }),
+        (Ok(min), Ok(max)) => Err(format!("minimum {} is greater than maximum {}", min, max)),
+        _ => Err(format!("numbers out of range in {:?}", line)),
+      },
+      None => Err(format!("expected \"<min>-<max> <letter>: <password>\", got {:?}", line)),
+    };
+
+    match parsed {
+      Ok(password) => database.passwords.push((idx + 1, password)),
+      Err(problem) => database.diagnostics.push((idx + 1, problem)),
+    }
+  }
+
+  database
+}
+
+fn database_to_text(database: &Database, policies: &[Box<dyn PasswordPolicy>]) -> String {
+  let mut lines = vec![];
+
+  for (line_number, password) in database.passwords.iter() {
+    let Policy { letter, min, max } = password.policy;
+    lines.push(format!("line {}: {}-{} {}: {}", line_number, min, max, letter, password.password));
+
+    for policy in policies {
+      match policy.check(password) {
+        Ok(()) => lines.push(format!("  {}: ok", policy.name())),
+        Err(violation) => lines.push(format!("  {}: {}", policy.name(), violation)),
       }
-      None => panic!("non-matching line in input: {}", line),
+    }
+  }
+
+  for (line_number, problem) in database.diagnostics.iter() {
+    lines.push(format!("line {}: malformed, {}", line_number, problem));
+  }
+
+  let counts = policies
+    .iter()
+    .map(|policy| {
+      let valid = database
+        .passwords
+        .iter()
+        .filter(|(_, password)| policy.is_valid(password))
+        .count();
+      format!("{} {}/{}", policy.name(), valid, database.passwords.len())
     })
-    .collect::<Vec<_>>()
+    .collect::<Vec<_>>();
+  lines.push(format!(
+    "Valid: {}; malformed lines: {}.",
+    counts.join(", "),
+    database.diagnostics.len()
+  ));
+
+  lines.join("\n")
+}
+
+fn database_to_json(database: &Database, policies: &[Box<dyn PasswordPolicy>]) -> Json {
+  let passwords = database
+    .passwords
+    .iter()
+    .map(|(line_number, password)| {
+      let violations = policies
+        .iter()
+        .filter_map(|policy| {
+          policy.check(password).err().map(|violation| {
+            Json::object(vec![
+              ("policy", Json::from(policy.name())),
+              ("violation", Json::from(violation)),
+            ])
+          })
+        })
+        .collect::<Vec<_>>();
+
+      Json::object(vec![
+        ("line", Json::from(*line_number)),
+        ("min", Json::from(password.policy.min)),
+        ("max", Json::from(password.policy.max)),
+        ("letter", Json::from(password.policy.letter.to_string())),
+        ("password", Json::from(password.password.as_str())),
+        ("violations", Json::Array(violations)),
+      ])
+    })
+    .collect::<Vec<_>>();
+
+  let diagnostics = database
+    .diagnostics
+    .iter()
+    .map(|(line_number, problem)| {
+      Json::object(vec![
+        ("line", Json::from(*line_number)),
+        ("problem", Json::from(problem.as_str())),
+      ])
+    })
+    .collect::<Vec<_>>();
+
+  let valid = policies
+    .iter()
+    .map(|policy| {
+      let valid = database
+        .passwords
+        .iter()
+        .filter(|(_, password)| policy.is_valid(password))
+        .count();
+      (policy.name(), Json::from(valid))
+    })
+    .collect::<Vec<_>>();
+
+  Json::object(vec![
+    ("passwords", Json::Array(passwords)),
+    ("diagnostics", Json::Array(diagnostics)),
+    ("valid", Json::Object(valid)),
+  ])
 }
 
 #[cfg(test)]
@@ -123,6 +492,88 @@ mod tests {
     );
   }
 
+  #[test]
+  fn policies_are_parsed_and_checked() {
+    let passwords = parse("1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc\n1-2 X: XXab1\n");
+
+    let counts = |spec: &str| {
+      let policies = parse_policies(spec).unwrap();
+      policies
+        .iter()
+        .map(|policy| (policy.name(), count_valid(&passwords, policy.as_ref())))
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+      counts("count; xor\nand"),
+      vec![("count".to_string(), 3), ("xor".to_string(), 1), ("and".to_string(), 2)]
+    );
+    assert_eq!(
+      counts("regex:^[a-z]+$;classes:upper,digit"),
+      vec![("regex:^[a-z]+$".to_string(), 3), ("classes:upper,digit".to_string(), 1)]
+    );
+
+    let passwords = parse("1-2 a: a!b\n1-2 a: Pa$$w0rd\n1-2 a: abc\n");
+    assert_eq!(passwords.len(), 3);
+    let punct = parse_policies("classes:punct").unwrap();
+    assert_eq!(count_valid(&passwords, punct[0].as_ref()), 2);
+
+    assert!(parse_policies("count;length").is_err());
+    assert!(parse_policies("classes:lower,emoji").is_err());
+    assert!(parse_policies("regex:(").is_err());
+  }
+
+  #[test]
+  fn malformed_lines_are_diagnosed() {
+    let database = parse_database("1-3 a: abcde\n\n3-1 b: cdefg\r\n2-9 c ccccccccc\n1-99999999999999999999 d: dd\n");
+
+    assert_eq!(database.passwords.len(), 1);
+    assert_eq!(database.passwords[0].0, 1);
+    assert_eq!(
+      database.diagnostics,
+      vec![
+        (3, "minimum 3 is greater than maximum 1".to_string()),
+        (
+          4,
+          "expected \"<min>-<max> <letter>: <password>\", got \"2-9 c ccccccccc\"".to_string()
+        ),
+        (5, "numbers out of range in \"1-99999999999999999999 d: dd\"".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn violations_are_reported() {
+    let database = parse_database("1-3 a: abcde\n1-3 b: cdefg\n2-9 c: ccccccccc\n2-9 c: cc\nfoo\n");
+    let policies = parse_policies("count;xor;and").unwrap();
+
+    assert_eq!(
+      database_to_text(&database, &policies),
+      "line 1: 1-3 a: abcde
+  count: ok
+  xor: ok
+  and: position 3 does not contain 'a'
+line 2: 1-3 b: cdefg
+  count: 'b' occurs 0 times, expected at least 1
+  xor: neither position 1 nor 3 contains 'b'
+  and: position 1 does not contain 'b'
+line 3: 2-9 c: ccccccccc
+  count: ok
+  xor: both positions 2 and 9 contain 'c'
+  and: ok
+line 4: 2-9 c: cc
+  count: ok
+  xor: position 9 is outside of the password
+  and: position 9 is outside of the password
+line 5: malformed, expected \"<min>-<max> <letter>: <password>\", got \"foo\"
+Valid: count 3/4, xor 1/4, and 1/4; malformed lines: 1."
+    );
+
+    let json = database_to_json(&database, &policies).to_string();
+    assert!(json.starts_with(r#"{"passwords":[{"line":1,"min":1,"max":3,"letter":"a","password":"abcde","violations":[{"policy":"and","violation":"position 3 does not contain 'a'"}]}"#));
+    assert!(json.ends_with(r#""valid":{"count":3,"xor":1,"and":1}}"#));
+  }
+
   #[test]
   fn part_one_solved() {
     let input = fs::read_to_string("inputs/d02").expect("can read day 2 input");
@@ -1,85 +1,426 @@
 use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use rayon::prelude::*;
+
+use crate::params::{any, parses, Param, Params};
+use crate::report::Report;
+
+pub const PARAMS: &[Param] = &[Param {
+  name: "slopes",
+  default: "3:1",
+  doc: "Comma-separated right:down slopes (right can be a fraction, like 1/2:1), the answer is the product of tree counts on them.",
+  check: |slopes| parse_slopes(slopes).map(|_| ()),
+}];
+
+pub const PARAMS2: &[Param] = &[Param {
+  name: "slopes",
+  default: "1:1,3:1,5:1,7:1,1:2",
+  doc: PARAMS[0].doc,
+  check: PARAMS[0].check,
+}];
+
+pub const OPTIMIZE_PARAMS: &[Param] = &[
+  Param {
+    name: "rights",
+    default: "0..=7",
+    doc: "Range of right numerators to search, like 3, 0..8 or 0..=7.",
+    check: |range| parse_range(range).map(|_| ()),
+  },
+  Param {
+    name: "denoms",
+    default: "1..=2",
+    doc: "Range of right denominators, 2 gives half-column slopes.",
+    check: |range| parse_range(range).map(|_| ()),
+  },
+  Param {
+    name: "downs",
+    default: "1..=2",
+    doc: "Range of down steps.",
+    check: |range| parse_range(range).map(|_| ()),
+  },
+  Param {
+    name: "starts",
+    default: "0",
+    doc: "Range of starting columns.",
+    check: |range| parse_range(range).map(|_| ()),
+  },
+  Param {
+    name: "top",
+    default: "5",
+    doc: "Number of routes to show with the fewest and the most trees.",
+    check: parses::<usize>,
+  },
+];
+
+pub const RENDER_PARAMS: &[Param] = &[
+  Param {
+    name: "slope",
+    default: "3:1",
+    doc: "Slope to follow, right:down or num/denom:down.",
+    check: parses::<Slope>,
+  },
+  Param {
+    name: "start",
+    default: "0",
+    doc: "Starting column.",
+    check: parses::<usize>,
+  },
+  Param {
+    name: "wrap",
+    default: "horizontal",
+    doc: "How the map repeats: horizontal, torus (both directions, until the route repeats) or none.",
+    check: parses::<Wrap>,
+  },
+  Param {
+    name: "out",
+    default: "",
+    doc: "File to write the rendered map to, printed if empty.",
+    check: any,
+  },
+];
 
 pub fn solve(input: &str) -> Option<Box<usize>> {
-  let tree_map = TreeMap::parse(input);
-  let trees_count = tree_map.tree_count_on_slope(3, 1);
-  Some(Box::new(trees_count))
+  solve_with(input, &Params::defaults(PARAMS))
 }
 
 pub fn solve2(input: &str) -> Option<Box<usize>> {
+  solve2_with(input, &Params::defaults(PARAMS2))
+}
+
+pub fn solve_with(input: &str, params: &Params) -> Option<Box<usize>> {
   let tree_map = TreeMap::parse(input);
 
-  let slopes = vec![(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
-  let answer = slopes.into_iter().fold(1, |answer, (right, down)| {
-    answer * tree_map.tree_count_on_slope(right, down)
-  });
+  let slopes = match parse_slopes(params.get_str("slopes")) {
+    Ok(slopes) => slopes,
+    Err(error) => {
+      error!("{}", error);
+      return None;
+    }
+  };
+  let answer = slopes
+    .par_iter()
+    .map(|slope| tree_map.tree_count_on_route(*slope, 0))
+    .product();
 
   Some(Box::new(answer))
 }
 
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<usize>> {
+  solve_with(input, params)
+}
+
+/// Searches the ranges of slopes and starting columns for the routes with the fewest and the most trees.
+pub fn optimize(input: &str, params: &Params) -> Option<Box<Report>> {
+  let tree_map = TreeMap::parse(input);
+  let range = |name: &str| {
+    parse_range(params.get_str(name)).map_err(|error| error!("invalid value for parameter {:?}: {}", name, error))
+  };
+  let (rights, denoms, downs, starts) = match (range("rights"), range("denoms"), range("downs"), range("starts")) {
+    (Ok(rights), Ok(denoms), Ok(downs), Ok(starts)) => (rights, denoms, downs, starts),
+    _ => return None,
+  };
+
+  let mut slopes = vec![];
+  for right in rights {
+    for denom in denoms.clone() {
+      for down in downs.clone() {
+        if denom > 0 && down > 0 {
+          slopes.push(Slope::new(right, denom, down));
+        }
+      }
+    }
+  }
+  slopes.sort_unstable();
+  slopes.dedup();
+
+  let routes = tree_map.rank_routes(&slopes, starts);
+  let top = params.get::<usize>("top").min(routes.len());
+  let describe = |(rank, route): (usize, &Route)| {
+    format!(
+      "  {}. {} from column {}: {} trees",
+      rank + 1,
+      route.slope,
+      route.start,
+      route.trees
+    )
+  };
+
+  let mut lines = vec!["Fewest trees:".to_string()];
+  lines.extend(routes.iter().take(top).enumerate().map(describe));
+  lines.push("Most trees:".to_string());
+  lines.extend(routes.iter().rev().take(top).enumerate().map(describe));
+  lines.push(format!("Searched {} routes.", routes.len()));
+
+  Some(Box::new(Report(lines.join("\n"))))
+}
+
+/// Draws the map with the route marked, `O` for open cells and `X` for trees hit.
+pub fn render(input: &str, params: &Params) -> Option<Box<Report>> {
+  let tree_map = TreeMap::parse(input);
+  let slope = params.get::<Slope>("slope");
+  let wrap = params.get::<Wrap>("wrap");
+
+  let route = tree_map.route_with(slope, params.get("start"), wrap);
+  let trees = route.iter().filter(|position| tree_map.is_tree(**position)).count();
+  let rendered = tree_map.render(&route, wrap);
+  let summary = format!("Slope {} hits {} trees in {} steps.", slope, trees, route.len());
+
+  let out = params.get_str("out");
+  if out.is_empty() {
+    return Some(Box::new(Report(format!("{}\n{}", rendered, summary))));
+  }
+
+  match fs::write(out, rendered + "\n") {
+    Ok(()) => Some(Box::new(Report(format!("{} Wrote the map to {:?}.", summary, out)))),
+    Err(error) => panic!("cannot write {:?}: {}", out, error),
+  }
+}
+
+/// Parses slopes like `1:1,3:1,1/2:1`.
+fn parse_slopes(slopes: &str) -> Result<Vec<Slope>, String> {
+  slopes.split(',').map(|slope| slope.parse()).collect()
+}
+
+/// Parses ranges like `3`, `0..8` or `0..=7`.
+fn parse_range(range: &str) -> Result<RangeInclusive<usize>, String> {
+  let number = |n: &str| {
+    n.trim()
+      .parse::<usize>()
+      .map_err(|_| format!("invalid range {:?}, expected n, a..b or a..=b", range))
+  };
+
+  if let Some((from, to)) = range.split_once("..=") {
+    Ok(number(from)?..=number(to)?)
+  } else if let Some((from, to)) = range.split_once("..") {
+    match number(to)?.checked_sub(1) {
+      Some(to) => Ok(number(from)?..=to),
+      None => Err(format!("range {:?} is empty", range)),
+    }
+  } else {
+    let n = number(range)?;
+    Ok(n..=n)
+  }
+}
+
+/// Moving `right_num / right_denom` columns right and `down` rows down on each step.
+///
+/// With a fractional right move, the column is rounded down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Slope {
+  right_num: usize,
+  right_denom: usize,
+  down: usize,
+}
+
+impl Slope {
+  /// Creates a slope, with the right move as a reduced fraction.
+  pub fn new(right_num: usize, right_denom: usize, down: usize) -> Slope {
+    assert!(right_denom > 0 && down > 0, "slope denominator and down step should be positive");
+
+    let gcd = gcd(right_num, right_denom);
+    Slope {
+      right_num: right_num / gcd,
+      right_denom: right_denom / gcd,
+      down,
+    }
+  }
+
+  /// Returns the position after `step` steps from column `start`.
+  fn position(&self, start: usize, step: usize) -> (usize, usize) {
+    (start + step * self.right_num / self.right_denom, step * self.down)
+  }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+  if b == 0 {
+    a
+  } else {
+    gcd(b, a % b)
+  }
+}
+
+impl fmt::Display for Slope {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.right_denom == 1 {
+      write!(f, "{}:{}", self.right_num, self.down)
+    } else {
+      write!(f, "{}/{}:{}", self.right_num, self.right_denom, self.down)
+    }
+  }
+}
+
+impl FromStr for Slope {
+  type Err = String;
+
+  fn from_str(slope: &str) -> Result<Slope, String> {
+    let error = || format!("invalid slope {:?}, expected right:down or num/denom:down", slope);
+
+    let (right, down) = slope.split_once(':').ok_or_else(error)?;
+    let (num, denom) = right.split_once('/').unwrap_or((right, "1"));
+    match (num.parse::<usize>(), denom.parse::<usize>(), down.parse::<usize>()) {
+      (Ok(num), Ok(denom), Ok(down)) if denom > 0 && down > 0 => Ok(Slope::new(num, denom, down)),
+      _ => Err(error()),
+    }
+  }
+}
+
+/// How the map repeats outside of its bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+  /// Repeated to the right, the route ends at the bottom row.
+  Horizontal,
+  /// Repeated in both directions, the route ends once it starts repeating.
+  Torus,
+  /// Not repeated, the route ends at the right or bottom edge.
+  None,
+}
+
+impl FromStr for Wrap {
+  type Err = String;
+
+  fn from_str(wrap: &str) -> Result<Wrap, String> {
+    match wrap {
+      "horizontal" => Ok(Wrap::Horizontal),
+      "torus" => Ok(Wrap::Torus),
+      "none" => Ok(Wrap::None),
+      _ => Err(format!("Unknown wrap mode {:?}, expected horizontal, torus or none.", wrap)),
+    }
+  }
+}
+
+/// A route down the map and the number of trees on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+  pub slope: Slope,
+  pub start: usize,
+  pub trees: usize,
+}
+
+/// Trees stored as a bitset per row, `words_per_row` 64-bit words each.
 #[derive(Debug)]
 struct TreeMap {
-  trees: HashSet<(usize, usize)>,
+  trees: Vec<u64>,
+  words_per_row: usize,
   width: usize,
   height: usize,
 }
 
 impl TreeMap {
   pub fn parse(input: &str) -> TreeMap {
-    let mut trees = HashSet::new();
-    let mut width = None;
-    let mut height = None;
+    let lines = input.trim_end().split('\n').collect::<Vec<_>>();
 
-    // y goes from top to bottom, starting from 0
-    for (y, line) in input.trim_end().split('\n').enumerate() {
-      if width == None {
-        width = Some(line.len());
-      }
-      height = Some(y + 1);
+    let width = lines[0].len();
+    let height = lines.len();
+    assert!(width > 0, "empty input is not valid");
+
+    let words_per_row = width.div_ceil(64);
+    let mut trees = vec![0; words_per_row * height];
 
-      for (x, char) in line.chars().enumerate() {
+    // y goes from top to bottom, starting from 0
+    for (y, line) in lines.into_iter().enumerate() {
+      for (x, char) in line.chars().take(width).enumerate() {
         // x goes from left to right, starting from 0
         if char == '#' {
-          trees.insert((x, y));
+          trees[y * words_per_row + x / 64] |= 1 << (x % 64);
         }
       }
     }
 
-    let width = width.expect("empty input is not valid");
-    let height = height.expect("empty input is not valid");
-    TreeMap { trees, width, height }
+    TreeMap {
+      trees,
+      words_per_row,
+      width,
+      height,
+    }
   }
 
   pub fn is_tree(&self, (x, y): (usize, usize)) -> bool {
     // emulate copying everything to the right infinite amount of times
     let x = x % self.width;
 
-    self.trees.contains(&(x, y))
+    y < self.height && self.trees[y * self.words_per_row + x / 64] & (1 << (x % 64)) != 0
   }
 
-  pub fn move_with_slope(&self, (x, y): (usize, usize), right: usize, down: usize) -> Option<(usize, usize)> {
-    let x = x + right;
-    let y = y + down;
+  /// Returns the positions visited going down from column `start` of the top row.
+  pub fn route(&self, slope: Slope, start: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+    (0..)
+      .map(move |step| slope.position(start, step))
+      .take_while(move |(_, y)| *y < self.height)
+  }
 
-    if y < self.height {
-      Some((x, y))
-    } else {
-      None
+  /// Returns the visited positions like `route`, wrapped with `wrap`.
+  ///
+  /// With `Wrap::Torus`, positions are inside the map and the route ends before the first repeated state.
+  pub fn route_with(&self, slope: Slope, start: usize, wrap: Wrap) -> Vec<(usize, usize)> {
+    match wrap {
+      Wrap::Horizontal => self.route(slope, start).collect(),
+      Wrap::None => self.route(slope, start).take_while(|(x, _)| *x < self.width).collect(),
+      Wrap::Torus => {
+        let mut seen = HashSet::new();
+
+        (0..)
+          .map(|step| {
+            let (x, y) = slope.position(start, step);
+            (x % self.width, y % self.height, step % slope.right_denom)
+          })
+          .take_while(|state| seen.insert(*state))
+          .map(|(x, y, _)| (x, y))
+          .collect()
+      }
     }
   }
 
-  pub fn tree_count_on_slope(&self, right: usize, down: usize) -> usize {
-    let mut trees_count = 0;
-    let mut position = Some((0, 0));
-    while let Some(curr_position) = position {
-      if self.is_tree(curr_position) {
-        trees_count += 1;
+  /// Draws the map, repeated to the right as far as `route` goes with `Wrap::Horizontal`.
+  pub fn render(&self, route: &[(usize, usize)], wrap: Wrap) -> String {
+    let visited = route.iter().cloned().collect::<HashSet<_>>();
+    let width = match wrap {
+      Wrap::Horizontal => {
+        let max_x = route.iter().map(|(x, _)| *x).max().unwrap_or(0);
+        (max_x / self.width + 1) * self.width
       }
+      Wrap::Torus | Wrap::None => self.width,
+    };
 
-      position = self.move_with_slope(curr_position, right, down);
-    }
+    (0..self.height)
+      .map(|y| {
+        (0..width)
+          .map(|x| match (visited.contains(&(x, y)), self.is_tree((x, y))) {
+            (true, true) => 'X',
+            (true, false) => 'O',
+            (false, true) => '#',
+            (false, false) => '.',
+          })
+          .collect::<String>()
+      })
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
 
-    trees_count
+  pub fn tree_count_on_route(&self, slope: Slope, start: usize) -> usize {
+    self.route(slope, start).filter(|position| self.is_tree(*position)).count()
+  }
+
+  /// Returns routes for all `slopes` and `starts`, from the fewest to the most trees.
+  ///
+  /// Slopes are evaluated in parallel.
+  pub fn rank_routes(&self, slopes: &[Slope], starts: RangeInclusive<usize>) -> Vec<Route> {
+    let mut routes = slopes
+      .par_iter()
+      .flat_map_iter(|slope| {
+        starts.clone().map(move |start| Route {
+          slope: *slope,
+          start,
+          trees: self.tree_count_on_route(*slope, start),
+        })
+      })
+      .collect::<Vec<_>>();
+
+    routes.sort_by_key(|route| (route.trees, route.slope, route.start));
+    routes
   }
 }
 
@@ -88,6 +429,18 @@ mod tests {
   use super::*;
   use std::fs;
 
+  const SAMPLE_INPUT: &str = "..##.......
+#...#...#..
+.#....#..#.
+..#.#...#.#
+.#...##..#.
+..#.##.....
+.#.#.#....#
+.#........#
+#.##...#...
+#...##....#
+.#..#...#.#";
+
   #[test]
   fn parser_works() {
     let input = fs::read_to_string("inputs/d03").expect("cannot read input for day 3");
@@ -96,16 +449,185 @@ mod tests {
     assert_eq!(parsed.width, 31);
     assert_eq!(parsed.height, 323);
 
-    assert!(parsed.trees.contains(&(8, 105)));
-    assert!(parsed.trees.contains(&(0, 105)));
-    assert!(parsed.trees.contains(&(30, 105)));
-    assert!(parsed.trees.contains(&(26, 322)));
+    assert!(parsed.is_tree((8, 105)));
+    assert!(parsed.is_tree((0, 105)));
+    assert!(parsed.is_tree((30, 105)));
+    assert!(parsed.is_tree((26, 322)));
+
+    assert!(!parsed.is_tree((9, 105)));
+    assert!(!parsed.is_tree((7, 105)));
+    assert!(!parsed.is_tree((29, 105)));
+    assert!(!parsed.is_tree((0, 0)));
+    assert!(!parsed.is_tree((0, 322)));
+    assert!(!parsed.is_tree((0, 323)));
+  }
+
+  /// The previous `HashSet` based implementation, kept for benchmarking.
+  struct HashSetTreeMap {
+    trees: HashSet<(usize, usize)>,
+    width: usize,
+    height: usize,
+  }
+
+  impl HashSetTreeMap {
+    fn parse(input: &str) -> HashSetTreeMap {
+      let lines = input.trim_end().split('\n').collect::<Vec<_>>();
+      let mut trees = HashSet::new();
+
+      for (y, line) in lines.iter().enumerate() {
+        for (x, char) in line.chars().enumerate() {
+          if char == '#' {
+            trees.insert((x, y));
+          }
+        }
+      }
+
+      HashSetTreeMap {
+        trees,
+        width: lines[0].len(),
+        height: lines.len(),
+      }
+    }
+
+    fn tree_count_on_route(&self, slope: Slope, start: usize) -> usize {
+      (0..)
+        .map(|step| slope.position(start, step))
+        .take_while(|(_, y)| *y < self.height)
+        .filter(|(x, y)| self.trees.contains(&(x % self.width, *y)))
+        .count()
+    }
+  }
+
+  /// Generates the same forest on every run, with roughly one tree per five cells.
+  fn generate_forest(width: usize, height: usize) -> String {
+    let mut state = 7u32;
+    let mut is_tree = || {
+      state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+      (state >> 16).is_multiple_of(5)
+    };
+
+    (0..height)
+      .map(|_| {
+        (0..width)
+          .map(|_| if is_tree() { '#' } else { '.' })
+          .collect::<String>()
+      })
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  #[test]
+  fn bitset_agrees_with_hash_set() {
+    let input = generate_forest(100, 1000);
+    let (bitset, hash_set) = (TreeMap::parse(&input), HashSetTreeMap::parse(&input));
+
+    for slope in parse_slopes("0:1,1:1,3:1,1/2:1,7/3:2,150:1").unwrap() {
+      for start in 0..=100 {
+        assert_eq!(
+          bitset.tree_count_on_route(slope, start),
+          hash_set.tree_count_on_route(slope, start)
+        );
+      }
+    }
+  }
+
+  /// Run with `cargo test bitset_benchmark -- --ignored --nocapture`.
+  #[ignore]
+  #[test]
+  fn bitset_benchmark() {
+    use std::time::Instant;
+
+    let input = generate_forest(31, 2_000_000);
+    let slopes = (1..=20).map(|right| Slope::new(right, 1, 1)).collect::<Vec<_>>();
+
+    let now = Instant::now();
+    let hash_set = HashSetTreeMap::parse(&input);
+    let expected = slopes
+      .iter()
+      .map(|slope| hash_set.tree_count_on_route(*slope, 0))
+      .collect::<Vec<_>>();
+    println!("HashSet: {:?}", now.elapsed());
+
+    let now = Instant::now();
+    let bitset = TreeMap::parse(&input);
+    let routes = bitset.rank_routes(&slopes, 0..=0);
+    println!("bitset with rayon: {:?}", now.elapsed());
+
+    for (slope, trees) in slopes.iter().zip(expected) {
+      assert!(routes.contains(&Route {
+        slope: *slope,
+        start: 0,
+        trees
+      }));
+    }
+  }
+
+  #[test]
+  fn rational_slopes_are_supported() {
+    let tree_map = TreeMap::parse(SAMPLE_INPUT);
+
+    assert_eq!("3:1".parse(), Ok(Slope::new(3, 1, 1)));
+    assert_eq!("2/4:1".parse(), Ok(Slope::new(1, 2, 1)));
+    assert_eq!(Slope::new(4, 2, 1).to_string(), "2:1");
+    assert_eq!(Slope::new(3, 2, 1).to_string(), "3/2:1");
+    assert!("1/0:1".parse::<Slope>().is_err());
+    assert!("3".parse::<Slope>().is_err());
+
+    let counts = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
+      .iter()
+      .map(|(right, down)| tree_map.tree_count_on_route(Slope::new(*right, 1, *down), 0))
+      .collect::<Vec<_>>();
+    assert_eq!(counts, vec![2, 7, 3, 4, 2]);
+
+    assert_eq!(
+      tree_map.route(Slope::new(1, 2, 1), 2).take(4).collect::<Vec<_>>(),
+      vec![(2, 0), (2, 1), (3, 2), (3, 3)]
+    );
+  }
+
+  #[test]
+  fn routes_are_ranked() {
+    let tree_map = TreeMap::parse(SAMPLE_INPUT);
+    let slopes = [Slope::new(1, 1, 1), Slope::new(3, 1, 1), Slope::new(7, 1, 1)];
+
+    let routes = tree_map.rank_routes(&slopes, 0..=0);
+    assert_eq!(
+      routes.iter().map(|route| (route.slope, route.trees)).collect::<Vec<_>>(),
+      vec![(slopes[0], 2), (slopes[2], 4), (slopes[1], 7)]
+    );
+
+    assert_eq!(tree_map.rank_routes(&slopes, 0..=10).len(), 33);
+    assert_eq!(parse_range("0..=7"), Ok(0..=7));
+    assert_eq!(parse_range("0..8"), Ok(0..=7));
+    assert_eq!(parse_range("3"), Ok(3..=3));
+    assert!(parse_range("0..0").is_err());
+  }
+
+  #[test]
+  fn routes_are_rendered() {
+    let tree_map = TreeMap::parse(SAMPLE_INPUT);
+    let slope = Slope::new(3, 1, 1);
+
+    let rendered = tree_map.render(&tree_map.route_with(slope, 0, Wrap::Horizontal), Wrap::Horizontal);
+    assert_eq!(
+      rendered.lines().take(3).collect::<Vec<_>>(),
+      vec![
+        "O.##.........##.........##.......",
+        "#..O#...#..#...#...#..#...#...#..",
+        ".#....X..#..#....#..#..#....#..#.",
+      ]
+    );
+    assert_eq!(rendered.lines().count(), 11);
+
+    let route = tree_map.route_with(slope, 0, Wrap::None);
+    assert_eq!(route, vec![(0, 0), (3, 1), (6, 2), (9, 3)]);
+    assert_eq!(tree_map.render(&route, Wrap::None).lines().nth(3), Some("..#.#...#O#"));
 
-    assert!(!parsed.trees.contains(&(9, 105)));
-    assert!(!parsed.trees.contains(&(7, 105)));
-    assert!(!parsed.trees.contains(&(29, 105)));
-    assert!(!parsed.trees.contains(&(0, 0)));
-    assert!(!parsed.trees.contains(&(0, 322)));
+    // 11 steps to get back to the first column, 11 to the first row
+    let route = tree_map.route_with(slope, 0, Wrap::Torus);
+    assert_eq!(route.len(), 11);
+    assert!(route.iter().all(|(x, y)| *x < 11 && *y < 11));
+    assert_eq!(tree_map.route_with(Slope::new(1, 2, 2), 0, Wrap::Torus).len(), 22);
   }
 
   #[test]
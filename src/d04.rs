@@ -1,106 +1,552 @@
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+use crate::json::Json;
+use crate::params::{any, parses, Param, Params};
+use crate::report::Report;
+
+pub const PARAMS: &[Param] = &[Param {
+  name: "schema",
+  default: "schemas/passport.schema",
+  doc: "Schema file with the fields, their presence and validators.",
+  check: |path| Schema::load(path).map(|_| ()),
+}];
+
+pub const PARAMS2: &[Param] = &[Param {
+  name: "schema",
+  default: "schemas/passport_strict.schema",
+  doc: PARAMS[0].doc,
+  check: PARAMS[0].check,
+}];
+
+pub const REPORT_PARAMS: &[Param] = PARAMS2;
+
+/// Default schemas are built in, so that they work outside of the repository too.
+const BUILTIN_SCHEMAS: [(&str, &str); 2] = [
+  ("schemas/passport.schema", include_str!("../schemas/passport.schema")),
+  (
+    "schemas/passport_strict.schema",
+    include_str!("../schemas/passport_strict.schema"),
+  ),
+];
+
+pub const EXPORT_PARAMS: &[Param] = &[
+  Param {
+    name: "format",
+    default: "jsonl",
+    doc: "Export format: jsonl (a JSON object per passport) or csv.",
+    check: parses::<ExportFormat>,
+  },
+  Param {
+    name: "out",
+    default: "",
+    doc: "File to write the export to, printed if empty.",
+    check: any,
+  },
+];
 
 pub fn solve(input: &str) -> Option<Box<usize>> {
-  let valid_passports = parse(input).iter().filter(|passport| is_valid(passport)).count();
-
-  Some(Box::new(valid_passports))
+  solve_with(input, &Params::defaults(PARAMS))
 }
 
 pub fn solve2(input: &str) -> Option<Box<usize>> {
-  let valid_passports = parse(input).iter().filter(|passport| is_valid2(passport)).count();
+  solve2_with(input, &Params::defaults(PARAMS2))
+}
+
+pub fn solve_with(input: &str, params: &Params) -> Option<Box<usize>> {
+  let schema = match Schema::load(params.get_str("schema")) {
+    Ok(schema) => schema,
+    Err(error) => {
+      error!("{}", error);
+      return None;
+    }
+  };
+  let valid_passports = parse(input).iter().filter(|passport| schema.is_valid(passport)).count();
 
   Some(Box::new(valid_passports))
 }
 
-fn parse(input: &str) -> Vec<HashMap<String, String>> {
-  input
-    .split("\n\n")
-    .map(|passport| {
-      passport
-        .split_ascii_whitespace()
-        .map(|kv| {
-          let mut kv = kv.split(':');
-          let key = kv.next().unwrap().to_string();
-          let value = kv.next().unwrap().to_string();
-          (key, value)
-        })
-        .collect::<HashMap<_, _>>()
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<usize>> {
+  solve_with(input, params)
+}
+
+/// Lists the issues of each passport by its starting line, followed by a histogram of the issues.
+pub fn report(input: &str, params: &Params) -> Option<Box<Report>> {
+  let schema = match Schema::load(params.get_str("schema")) {
+    Ok(schema) => schema,
+    Err(error) => {
+      error!("{}", error);
+      return None;
+    }
+  };
+
+  let mut lines = vec![];
+  let mut histogram = HashMap::new();
+  let mut valid_count = 0;
+
+  let batch = parse_batch(input);
+  for (line_number, error) in batch.errors.iter() {
+    *histogram.entry("malformed record".to_string()).or_insert(0) += 1;
+    lines.push(format!("line {}: malformed, {}", line_number, error));
+  }
+
+  let passports = batch.passports;
+  for (line_number, passport) in passports.iter() {
+    let issues = schema.issues(passport);
+    if issues.is_empty() {
+      valid_count += 1;
+      lines.push(format!("line {}: ok", line_number));
+      continue;
+    }
+
+    for issue in issues.iter() {
+      *histogram.entry(issue.kind()).or_insert(0) += 1;
+    }
+
+    let issues = issues.iter().map(|issue| issue.to_string()).collect::<Vec<_>>();
+    lines.push(format!("line {}: {}", line_number, issues.join(", ")));
+  }
+
+  let mut histogram = histogram.into_iter().collect::<Vec<_>>();
+  histogram.sort_by(|(kind1, count1), (kind2, count2)| count2.cmp(count1).then(kind1.cmp(kind2)));
+
+  lines.push("Issues:".to_string());
+  lines.extend(histogram.iter().map(|(kind, count)| format!("  {:>5} {}", count, kind)));
+  lines.push(format!("Valid: {}/{}.", valid_count, passports.len()));
+
+  Some(Box::new(Report(lines.join("\n"))))
+}
+
+/// Exports parsed passports for other tools, records with errors are skipped.
+pub fn export(input: &str, params: &Params) -> Option<Box<Report>> {
+  let batch = parse_batch(input);
+  for (line_number, error) in batch.errors.iter() {
+    warn!("Skipping the record at line {}: {}", line_number, error);
+  }
+
+  let exported = match params.get::<ExportFormat>("format") {
+    ExportFormat::JsonLines => to_json_lines(&batch.passports),
+    ExportFormat::Csv => to_csv(&batch.passports),
+  };
+
+  let out = params.get_str("out");
+  if out.is_empty() {
+    return Some(Box::new(Report(exported)));
+  }
+
+  match fs::write(out, exported + "\n") {
+    Ok(()) => Some(Box::new(Report(format!(
+      "Wrote {} passports to {:?}.",
+      batch.passports.len(),
+      out
+    )))),
+    Err(error) => panic!("cannot write {:?}: {}", out, error),
+  }
+}
+
+type Passport = HashMap<String, String>;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Batch {
+  /// Passports together with the line numbers they start at.
+  passports: Vec<(usize, Passport)>,
+  /// Line numbers and problems that made records unusable.
+  errors: Vec<(usize, String)>,
+}
+
+/// Parses passports, logging records that could not be parsed.
+fn parse(input: &str) -> Vec<Passport> {
+  let batch = parse_batch(input);
+  for (line_number, error) in batch.errors.iter() {
+    warn!("Skipping the record at line {}: {}", line_number, error);
+  }
+
+  batch.passports.into_iter().map(|(_, passport)| passport).collect()
+}
+
+/// Parses passports separated by one or more blank lines.
+///
+/// Records with fields lacking a `:` or with duplicate keys are rejected,
+/// and their problems are collected with the line numbers.
+fn parse_batch(input: &str) -> Batch {
+  let mut batch = Batch {
+    passports: vec![],
+    errors: vec![],
+  };
+  let mut record: Option<Record> = None;
+
+  // `lines` also strips `\r` of CRLF line endings
+  for (idx, line) in input.lines().enumerate() {
+    let line_number = idx + 1;
+    if line.trim().is_empty() {
+      if let Some(record) = record.take() {
+        batch.add(record);
+      }
+      continue;
+    }
+
+    let record = record.get_or_insert_with(|| Record {
+      start: line_number,
+      passport: HashMap::new(),
+      errors: vec![],
+    });
+    for kv in line.split_ascii_whitespace() {
+      match kv.split_once(':') {
+        None => record.errors.push((line_number, format!("missing ':' in {:?}", kv))),
+        Some((key, _)) if record.passport.contains_key(key) => {
+          record.errors.push((line_number, format!("duplicate key {:?}", key)))
+        }
+        Some((key, value)) => {
+          record.passport.insert(key.to_string(), value.to_string());
+        }
+      }
+    }
+  }
+
+  if let Some(record) = record {
+    batch.add(record);
+  }
+
+  batch
+}
+
+/// A passport being parsed, with the line it starts at and the errors found so far.
+struct Record {
+  start: usize,
+  passport: Passport,
+  errors: Vec<(usize, String)>,
+}
+
+impl Batch {
+  fn add(&mut self, mut record: Record) {
+    if record.errors.is_empty() {
+      self.passports.push((record.start, record.passport));
+    } else {
+      self.errors.append(&mut record.errors);
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+  JsonLines,
+  Csv,
+}
+
+impl FromStr for ExportFormat {
+  type Err = String;
+
+  fn from_str(format: &str) -> Result<ExportFormat, String> {
+    match format {
+      "jsonl" => Ok(ExportFormat::JsonLines),
+      "csv" => Ok(ExportFormat::Csv),
+      _ => Err(format!("Unknown export format {:?}, expected jsonl or csv.", format)),
+    }
+  }
+}
+
+/// Sorted keys present in any of the passports.
+fn all_keys(passports: &[(usize, Passport)]) -> Vec<&str> {
+  let keys = passports
+    .iter()
+    .flat_map(|(_, passport)| passport.keys().map(|key| key.as_str()))
+    .collect::<BTreeSet<_>>();
+
+  keys.into_iter().collect()
+}
+
+/// A JSON object per line, with the starting line and the sorted fields.
+fn to_json_lines(passports: &[(usize, Passport)]) -> String {
+  passports
+    .iter()
+    .map(|(line_number, passport)| {
+      let mut fields = passport.iter().collect::<Vec<_>>();
+      fields.sort();
+
+      let mut object = vec![("line".to_string(), Json::from(*line_number))];
+      object.extend(
+        fields
+          .into_iter()
+          .map(|(key, value)| (key.clone(), Json::from(value.as_str()))),
+      );
+      Json::Object(object).to_string()
     })
     .collect::<Vec<_>>()
+    .join("\n")
 }
 
-fn is_valid(passport: &HashMap<String, String>) -> bool {
-  let keys = passport.keys().collect::<HashSet<_>>();
+/// A header with `line` and all keys, followed by a row per passport with empty cells for missing fields.
+fn to_csv(passports: &[(usize, Passport)]) -> String {
+  let escape = |cell: &str| {
+    if cell.contains([',', '"', '\n']) {
+      format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+      cell.to_string()
+    }
+  };
+
+  let keys = all_keys(passports);
+  let mut rows = vec![format!("line,{}", keys.iter().map(|key| escape(key)).collect::<Vec<_>>().join(","))];
+
+  for (line_number, passport) in passports {
+    let cells = keys
+      .iter()
+      .map(|key| passport.get(*key).map(|value| escape(value)).unwrap_or_default())
+      .collect::<Vec<_>>();
+    rows.push(format!("{},{}", line_number, cells.join(",")));
+  }
+
+  rows.join("\n")
+}
 
-  let required = vec!["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"];
-  required.iter().all(|field| keys.contains(&field.to_string()))
+/// Fields of a document type, loaded from a schema file.
+#[derive(Debug)]
+struct Schema {
+  fields: Vec<Field>,
 }
 
-fn is_valid2(passport: &HashMap<String, String>) -> bool {
-  if is_valid(passport) {
-    // we know that all fields are present, since `is_valid` checks it
-    let valid_byr = is_valid_year(passport.get("byr").unwrap(), 1920, 2002);
-    let valid_iyr = is_valid_year(passport.get("iyr").unwrap(), 2010, 2020);
-    let valid_eyr = is_valid_year(passport.get("eyr").unwrap(), 2020, 2030);
+#[derive(Debug)]
+struct Field {
+  name: String,
+  required: bool,
+  validator: Validator,
+}
 
-    let valid_hgt = is_valid_height(passport.get("hgt").unwrap());
-    let valid_hcl = is_valid_hcl(passport.get("hcl").unwrap());
-    let valid_ecl = is_valid_ecl(passport.get("ecl").unwrap());
-    let valid_pid = is_valid_pid(passport.get("pid").unwrap());
+#[derive(Debug)]
+enum Validator {
+  Any,
+  /// An integer between `min` and `max` (inclusive).
+  Range(i64, i64),
+  /// An integer followed by one of the units, each with its own inclusive range.
+  Units(Vec<(String, i64, i64)>),
+  Regex(Regex),
+  Enum(Vec<String>),
+  /// Exactly this many decimal digits.
+  Digits(usize),
+}
 
-    valid_byr && valid_iyr && valid_eyr && valid_hgt && valid_hcl && valid_ecl && valid_pid
-  } else {
-    false
+impl Schema {
+  /// Loads the schema at `path`, the built-in copy of a default schema is used only if it can't be read.
+  pub fn load(path: &str) -> Result<Schema, String> {
+    let schema = match fs::read_to_string(path) {
+      Ok(schema) => schema,
+      Err(error) => match BUILTIN_SCHEMAS.iter().find(|(builtin, _)| *builtin == path) {
+        Some((_, schema)) => schema.to_string(),
+        None => return Err(format!("Cannot read schema {:?} due to {:?}.", path, error)),
+      },
+    };
+
+    Schema::parse(&schema).map_err(|(line_number, error)| format!("{}:{}: {}", path, line_number, error))
+  }
+
+  /// Parses `<field> required|optional [validator args...]` lines, skipping blank lines and `#` comments.
+  pub fn parse(schema: &str) -> Result<Schema, (usize, String)> {
+    let mut fields = vec![];
+
+    for (idx, line) in schema.lines().enumerate() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+
+      fields.push(Field::parse(line).map_err(|error| (idx + 1, error))?);
+    }
+
+    Ok(Schema { fields })
+  }
+
+  /// Checks that all required fields are present, and all known fields are valid.
+  pub fn is_valid(&self, passport: &Passport) -> bool {
+    self.issues(passport).is_empty()
+  }
+
+  /// Returns missing required fields and invalid values, in the order of the schema fields.
+  pub fn issues(&self, passport: &Passport) -> Vec<Issue> {
+    self
+      .fields
+      .iter()
+      .filter_map(|field| match passport.get(&field.name) {
+        Some(value) => field.validator.check(value).err().map(|problem| Issue::Invalid {
+          field: field.name.clone(),
+          problem,
+          value: value.clone(),
+        }),
+        None if field.required => Some(Issue::Missing(field.name.clone())),
+        None => None,
+      })
+      .collect()
   }
 }
 
-fn is_valid_year(year: &str, min: u32, max: u32) -> bool {
-  match year.parse::<u32>() {
-    Ok(year) => year >= min && year <= max,
-    Err(_) => false,
+impl Field {
+  fn parse(line: &str) -> Result<Field, String> {
+    let (name, rest) = split_word(line);
+    let (presence, rest) = split_word(rest);
+    let (kind, args) = split_word(rest);
+
+    let required = match presence {
+      "required" => true,
+      "optional" => false,
+      _ => return Err(format!("expected required or optional for {:?}, got {:?}", name, presence)),
+    };
+
+    Ok(Field {
+      name: name.to_string(),
+      required,
+      validator: Validator::parse(kind, args)?,
+    })
   }
 }
 
-fn is_valid_height(height: &str) -> bool {
-  lazy_static! {
-    static ref RE: Regex = Regex::new(r"(?P<number>\d+)(?P<unit>cm|in)").unwrap();
+/// Splits `line` into the first word and the rest.
+fn split_word(line: &str) -> (&str, &str) {
+  let line = line.trim_start();
+  match line.split_once(char::is_whitespace) {
+    Some((word, rest)) => (word, rest.trim_start()),
+    None => (line, ""),
   }
+}
 
-  match RE.captures_iter(height).next() {
-    Some(capture) => match (capture.name("number"), capture.name("unit")) {
-      (Some(number), Some(unit)) => match number.as_str().parse::<u32>() {
-        Ok(number) => match unit.as_str() {
-          "cm" => number >= 150 && number <= 193,
-          "in" => number >= 59 && number <= 76,
-          _ => false,
-        },
-        Err(_) => false,
+impl Validator {
+  fn parse(kind: &str, args: &str) -> Result<Validator, String> {
+    let words = args.split_ascii_whitespace().collect::<Vec<_>>();
+    let number = |n: &str| {
+      n.parse::<i64>()
+        .map_err(|_| format!("invalid number {:?} in {} validator", n, kind))
+    };
+
+    match (kind, &words[..]) {
+      ("" | "any", []) => Ok(Validator::Any),
+      ("range", [min, max]) => Ok(Validator::Range(number(min)?, number(max)?)),
+      ("units", units) if !units.is_empty() => Ok(Validator::Units(
+        units
+          .iter()
+          .map(|unit| match unit.split_once('=').and_then(|(unit, range)| Some((unit, range.split_once('-')?))) {
+            Some((unit, (min, max))) => Ok((unit.to_string(), number(min)?, number(max)?)),
+            None => Err(format!("expected <unit>=<min>-<max>, got {:?}", unit)),
+          })
+          .collect::<Result<Vec<_>, _>>()?,
+      )),
+      ("regex", _) if !args.is_empty() => Ok(Validator::Regex(
+        Regex::new(args).map_err(|error| format!("invalid regex {:?}: {}", args, error))?,
+      )),
+      ("enum", values) if !values.is_empty() => Ok(Validator::Enum(
+        values.iter().map(|value| value.to_string()).collect(),
+      )),
+      ("digits", [count]) => Ok(Validator::Digits(
+        count
+          .parse()
+          .map_err(|_| format!("invalid digit count {:?}", count))?,
+      )),
+      _ => Err(format!(
+        "invalid validator {:?}, expected any, range <min> <max>, units <unit>=<min>-<max>..., regex <pattern>, enum <value>... or digits <count>",
+        format!("{} {}", kind, args).trim()
+      )),
+    }
+  }
+
+  #[cfg(test)]
+  fn is_valid(&self, value: &str) -> bool {
+    self.check(value).is_ok()
+  }
+
+  fn check(&self, value: &str) -> Result<(), Problem> {
+    lazy_static! {
+      static ref UNIT_RE: Regex = Regex::new(r"^(?P<number>\d+)(?P<unit>[a-z]*)$").unwrap();
+    }
+
+    let in_range = |number: i64, min: i64, max: i64| {
+      if number >= min && number <= max {
+        Ok(())
+      } else {
+        Err(Problem::OutOfRange)
+      }
+    };
+
+    match self {
+      Validator::Any => Ok(()),
+      Validator::Range(min, max) => match value.parse::<i64>() {
+        Ok(number) => in_range(number, *min, *max),
+        Err(_) => Err(Problem::NotANumber),
       },
-      _ => false,
-    },
-    None => false,
+      Validator::Units(units) => {
+        let cap = UNIT_RE.captures(value).ok_or(Problem::NotANumber)?;
+        let number = cap["number"].parse::<i64>().map_err(|_| Problem::NotANumber)?;
+
+        match units.iter().find(|(unit, _, _)| *unit == cap["unit"]) {
+          Some((_, min, max)) => in_range(number, *min, *max),
+          None => Err(Problem::BadUnit),
+        }
+      }
+      Validator::Regex(re) if re.is_match(value) => Ok(()),
+      Validator::Regex(_) => Err(Problem::Malformed),
+      Validator::Enum(values) if values.iter().any(|valid| valid == value) => Ok(()),
+      Validator::Enum(_) => Err(Problem::UnknownValue),
+      Validator::Digits(_) if !value.chars().all(|ch| ch.is_ascii_digit()) => Err(Problem::NotDigits),
+      Validator::Digits(count) if value.len() != *count => Err(Problem::WrongLength),
+      Validator::Digits(_) => Ok(()),
+    }
   }
 }
 
-fn is_valid_hcl(hcl: &str) -> bool {
-  lazy_static! {
-    static ref RE: Regex = Regex::new(r"^#[0-9a-f]{6}$").unwrap();
+/// Why a value was rejected by its validator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Problem {
+  NotANumber,
+  OutOfRange,
+  BadUnit,
+  Malformed,
+  UnknownValue,
+  NotDigits,
+  WrongLength,
+}
+
+impl fmt::Display for Problem {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let description = match self {
+      Problem::NotANumber => "not a number",
+      Problem::OutOfRange => "out of range",
+      Problem::BadUnit => "bad unit",
+      Problem::Malformed => "malformed",
+      Problem::UnknownValue => "unknown value",
+      Problem::NotDigits => "not digits",
+      Problem::WrongLength => "wrong length",
+    };
+
+    write!(f, "{}", description)
   }
+}
 
-  RE.is_match(hcl)
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Issue {
+  Missing(String),
+  Invalid {
+    field: String,
+    problem: Problem,
+    value: String,
+  },
 }
 
-fn is_valid_ecl(ecl: &str) -> bool {
-  lazy_static! {
-    static ref VALID_ECLS: Vec<&'static str> = vec!["amb", "blu", "brn", "gry", "grn", "hzl", "oth"];
+impl Issue {
+  /// The issue without the value, for grouping issues across passports.
+  fn kind(&self) -> String {
+    match self {
+      Issue::Missing(field) => format!("missing {}", field),
+      Issue::Invalid { field, problem, .. } => format!("{} {}", field, problem),
+    }
   }
-
-  VALID_ECLS.contains(&ecl)
 }
 
-fn is_valid_pid(pid: &str) -> bool {
-  pid.len() == 9 && pid.chars().all(|ch| ch.is_ascii_digit())
+impl fmt::Display for Issue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Issue::Missing(_) => write!(f, "{}", self.kind()),
+      Issue::Invalid { value, .. } => write!(f, "{} ({:?})", self.kind(), value),
+    }
+  }
 }
 
 #[cfg(test)]
@@ -151,26 +597,50 @@ mod tests {
 
   #[test]
   fn validators_work() {
-    assert!(is_valid_year("2002", 1920, 2002));
-
-    assert!(!is_valid_year("2003", 1920, 2002));
-
-    assert!(is_valid_height("60in"));
-    assert!(is_valid_height("190cm"));
-
-    assert!(!is_valid_height("190in"));
-    assert!(!is_valid_height("190"));
-
-    assert!(is_valid_hcl("#123abc"));
-
-    assert!(!is_valid_hcl("#123abz"));
-    assert!(!is_valid_hcl("123abc"));
-
-    assert!(is_valid_ecl("brn"));
-    assert!(!is_valid_ecl("wat"));
+    let validator = |spec: &str| {
+      let (kind, args) = split_word(spec);
+      Validator::parse(kind, args).unwrap()
+    };
+
+    let year = validator("range 1920 2002");
+    assert!(year.is_valid("2002"));
+    assert!(!year.is_valid("2003"));
+
+    let height = validator("units cm=150-193 in=59-76");
+    assert!(height.is_valid("60in"));
+    assert!(height.is_valid("190cm"));
+    assert!(!height.is_valid("190in"));
+    assert!(!height.is_valid("190"));
+
+    let hcl = validator("regex ^#[0-9a-f]{6}$");
+    assert!(hcl.is_valid("#123abc"));
+    assert!(!hcl.is_valid("#123abz"));
+    assert!(!hcl.is_valid("123abc"));
+
+    let ecl = validator("enum amb blu brn gry grn hzl oth");
+    assert!(ecl.is_valid("brn"));
+    assert!(!ecl.is_valid("wat"));
+
+    let pid = validator("digits 9");
+    assert!(pid.is_valid("000000001"));
+    assert!(!pid.is_valid("0123456789"));
+  }
 
-    assert!(is_valid_pid("000000001"));
-    assert!(!is_valid_pid("0123456789"));
+  #[test]
+  fn schemas_are_parsed() {
+    assert!(Schema::load("schemas/passport.schema").is_ok());
+    assert!(Schema::load("schemas/north_pole.schema").is_ok());
+
+    assert_eq!(
+      Schema::parse("# fields\nbyr required range 1920\n").unwrap_err(),
+      (
+        2,
+        "invalid validator \"range 1920\", expected any, range <min> <max>, units <unit>=<min>-<max>..., regex <pattern>, enum <value>... or digits <count>".to_string()
+      )
+    );
+    assert!(Schema::parse("byr mandatory").is_err());
+    assert!(Schema::parse("hgt required units cm=150").is_err());
+    assert!(Schema::parse("hcl required regex (").is_err());
   }
 
   #[test]
@@ -180,7 +650,12 @@ mod tests {
       .next()
       .unwrap();
 
-    assert!(is_valid2(&passport));
+    let strict = Schema::load("schemas/passport_strict.schema").unwrap();
+    assert!(strict.is_valid(&passport));
+
+    // North Pole credentials need a country ID
+    let north_pole = Schema::load("schemas/north_pole.schema").unwrap();
+    assert!(!north_pole.is_valid(&passport));
   }
 
   #[test]
@@ -221,6 +696,99 @@ pid:3556412378 byr:2007
     assert_eq!(solve2(invalid_passports), Some(Box::new(0)));
   }
 
+  #[test]
+  fn issues_are_reported() {
+    let input = r#"eyr:1972 cid:100
+hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926
+
+iyr:2019
+hcl:#602927 eyr:1967 hgt:170cm
+ecl:grn pid:012533040 byr:1946
+
+hcl:dab227 iyr:2012
+ecl:brn hgt:182in pid:021572410 eyr:2020 byr:1992 cid:277
+
+hgt:59cm ecl:zzz
+eyr:2038 hcl:74454a iyr:2023
+pid:3556412378
+
+pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f
+"#;
+
+    let schema = Schema::load("schemas/passport_strict.schema").unwrap();
+    let passports = parse_batch(input).passports;
+    assert_eq!(
+      passports.iter().map(|(line_number, _)| *line_number).collect::<Vec<_>>(),
+      vec![1, 4, 8, 11, 15]
+    );
+
+    let issues = passports
+      .iter()
+      .map(|(_, passport)| {
+        let issues = schema.issues(passport);
+        issues.iter().map(|issue| issue.to_string()).collect::<Vec<_>>()
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      issues,
+      vec![
+        vec![
+          "eyr out of range (\"1972\")",
+          "hgt bad unit (\"170\")",
+          "pid not digits (\"186cm\")"
+        ],
+        vec!["eyr out of range (\"1967\")"],
+        vec!["hgt out of range (\"182in\")", "hcl malformed (\"dab227\")"],
+        vec![
+          "missing byr",
+          "iyr out of range (\"2023\")",
+          "eyr out of range (\"2038\")",
+          "hgt out of range (\"59cm\")",
+          "hcl malformed (\"74454a\")",
+          "ecl unknown value (\"zzz\")",
+          "pid wrong length (\"3556412378\")"
+        ],
+        vec![],
+      ]
+    );
+
+    let params = Params::defaults(REPORT_PARAMS);
+    let report = report(input, &params).unwrap().0;
+    assert!(report.starts_with("line 1: eyr out of range"));
+    assert!(report.ends_with("Issues:\n      3 eyr out of range\n      2 hcl malformed\n      2 hgt out of range\n      1 ecl unknown value\n      1 hgt bad unit\n      1 iyr out of range\n      1 missing byr\n      1 pid not digits\n      1 pid wrong length\nValid: 1/5."));
+  }
+
+  #[test]
+  fn records_are_parsed_robustly() {
+    let batch = parse_batch("\r\nbyr:1937 cid:147\r\niyr:2017\r\n\r\n\r\n\nhgt:183cm ecl\npid:1 pid:2\n\n\nhcl:#fffffd\n\n\n");
+
+    assert_eq!(batch.passports.len(), 2);
+    assert_eq!(batch.passports[0].0, 2);
+    assert_eq!(batch.passports[0].1.get("iyr"), Some(&"2017".to_string()));
+    assert_eq!(batch.passports[1].0, 11);
+    assert_eq!(
+      batch.errors,
+      vec![
+        (7, "missing ':' in \"ecl\"".to_string()),
+        (8, "duplicate key \"pid\"".to_string())
+      ]
+    );
+  }
+
+  #[test]
+  fn passports_are_exported() {
+    let passports = parse_batch("byr:1937 cid:147\n\nhcl:#fff,\"fd\" byr:2000\n").passports;
+
+    assert_eq!(
+      to_json_lines(&passports),
+      "{\"line\":1,\"byr\":\"1937\",\"cid\":\"147\"}\n{\"line\":3,\"byr\":\"2000\",\"hcl\":\"#fff,\\\"fd\\\"\"}"
+    );
+    assert_eq!(
+      to_csv(&passports),
+      "line,byr,cid,hcl\n1,1937,147,\n3,2000,,\"#fff,\"\"fd\"\"\""
+    );
+  }
+
   #[test]
   fn part_two_solved() {
     let input = fs::read_to_string("inputs/d04").unwrap();
@@ -1,79 +1,376 @@
-use std::collections::HashSet;
+use crate::params::{parses, Param, Params};
+use crate::report::Report;
+
+pub const PARAMS: &[Param] = &[
+  Param {
+    name: "row_bits",
+    default: "7",
+    doc: "Number of letters encoding the row.",
+    check: parses::<u32>,
+  },
+  Param {
+    name: "col_bits",
+    default: "3",
+    doc: "Number of letters encoding the column.",
+    check: parses::<u32>,
+  },
+  Param {
+    name: "row_letters",
+    default: "FB",
+    doc: "Letters for the lower and upper halves of the rows.",
+    check: |letters| parse_letters(letters).map(|_| ()),
+  },
+  Param {
+    name: "col_letters",
+    default: "LR",
+    doc: "Letters for the lower and upper halves of the columns.",
+    check: |letters| parse_letters(letters).map(|_| ()),
+  },
+];
 
 pub fn solve(input: &str) -> Option<Box<u32>> {
-  input
-    .trim_end()
-    .split_ascii_whitespace()
-    .map(|pass| seat_id(pass))
-    .max()
-    .map(|max_seat_id| Box::new(max_seat_id))
+  solve_with(input, &Params::defaults(PARAMS))
 }
 
 pub fn solve2(input: &str) -> Option<Box<u32>> {
-  let all_seat_ids = input
-    .trim_end()
+  let codec = Codec::default();
+  let seat_map = SeatMap::new(codec, &parse(input, &codec));
+
+  match seat_map.candidates()[..] {
+    [candidate] => Some(Box::new(candidate.first)),
+    ref candidates => {
+      warn!("Expected one single free seat, found {}.", candidates.len());
+      None
+    }
+  }
+}
+
+pub fn solve_with(input: &str, params: &Params) -> Option<Box<u32>> {
+  let codec = match Codec::from_params(params) {
+    Ok(codec) => codec,
+    Err(error) => {
+      error!("{}", error);
+      return None;
+    }
+  };
+
+  parse(input, &codec).into_iter().max().map(Box::new)
+}
+
+/// Returns the only free seat between two taken ones, or all such candidates with the gaps they are in.
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<Report>> {
+  let codec = match Codec::from_params(params) {
+    Ok(codec) => codec,
+    Err(error) => {
+      error!("{}", error);
+      return None;
+    }
+  };
+  let seat_map = SeatMap::new(codec, &parse(input, &codec));
+
+  match seat_map.candidates()[..] {
+    [] => {
+      let gaps = seat_map.gaps().iter().map(|gap| gap.describe(&codec)).collect::<Vec<_>>();
+      warn!("Expected a single free seat, found none; gaps:\n{}", gaps.join("\n"));
+      None
+    }
+    [candidate] => Some(Box::new(Report(candidate.first.to_string()))),
+    ref candidates => {
+      let mut lines = vec![format!("{} candidate seats:", candidates.len())];
+      lines.extend(candidates.iter().map(|gap| format!("  {}", gap.describe(&codec))));
+      Some(Box::new(Report(lines.join("\n"))))
+    }
+  }
+}
+
+/// Renders taken (`#`) and free (`.`) seats of every row, followed by the gaps between taken seats.
+pub fn seat_map(input: &str, params: &Params) -> Option<Box<Report>> {
+  let codec = match Codec::from_params(params) {
+    Ok(codec) => codec,
+    Err(error) => {
+      error!("{}", error);
+      return None;
+    }
+  };
+  let seat_map = SeatMap::new(codec, &parse(input, &codec));
+
+  let mut lines = vec![seat_map.render(), "Gaps:".to_string()];
+  lines.extend(seat_map.gaps().iter().map(|gap| format!("  {}", gap.describe(&codec))));
+
+  Some(Box::new(Report(lines.join("\n"))))
+}
+
+/// Decodes seat IDs of the passes, logging and skipping invalid ones.
+fn parse(input: &str, codec: &Codec) -> Vec<u32> {
+  input
     .split_ascii_whitespace()
-    .map(|pass| seat_id(pass))
-    .collect::<HashSet<_>>();
-
-  let mut candidates = all_seat_ids
-    .iter()
-    .filter_map(|seat_id| {
-      if all_seat_ids.contains(&(seat_id + 2)) {
-        Some(seat_id + 1)
-      } else if all_seat_ids.contains(&(seat_id - 2)) {
-        Some(seat_id - 1)
-      } else {
+    .filter_map(|pass| match codec.decode(pass) {
+      Ok(seat) => Some(codec.seat_id(seat)),
+      Err(error) => {
+        warn!("Skipping boarding pass {:?}: {}", pass, error);
         None
       }
     })
-    .filter(|candidate| !all_seat_ids.contains(candidate))
-    .collect::<HashSet<_>>();
+    .collect()
+}
+
+/// Limits planes to about a million seats.
+const MAX_SEAT_BITS: u32 = 20;
 
-  if candidates.len() == 1 {
-    candidates.drain().next().map(|candidate| Box::new(candidate))
-  } else {
-    panic!("more than one candidate: {:?}", candidates);
+/// Parses a pair of different letters, for the lower and the upper half.
+fn parse_letters(letters: &str) -> Result<[char; 2], String> {
+  match letters.chars().collect::<Vec<_>>()[..] {
+    [lower, upper] if lower != upper => Ok([lower, upper]),
+    _ => Err(format!("expected two different letters, got {:?}", letters)),
   }
 }
 
-fn seat_id(pass: &str) -> u32 {
-  let (row, col) = decode_boarding_pass(pass);
-  row * 8 + col
+/// Converts between boarding passes and `(row, col)` seats.
+///
+/// A pass is `row_bits` row letters followed by `col_bits` column letters,
+/// each letter picking the lower or the upper half of the remaining rows or columns,
+/// so the letters are binary digits with the first letter of the pair being `0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Codec {
+  row_bits: u32,
+  col_bits: u32,
+  row_letters: [char; 2],
+  col_letters: [char; 2],
 }
 
-fn decode_boarding_pass(pass: &str) -> (u32, u32) {
-  if pass.len() != 10 {
-    panic!("Invalid length = {}", pass.len());
+impl Default for Codec {
+  fn default() -> Codec {
+    Codec {
+      row_bits: 7,
+      col_bits: 3,
+      row_letters: ['F', 'B'],
+      col_letters: ['L', 'R'],
+    }
   }
-
-  let row = extract_from_range(&pass[..7], 0, 127).unwrap();
-  let col = extract_from_range(&pass[7..], 0, 7).unwrap();
-  (row, col)
 }
 
-fn extract_from_range(divisions: &str, min: u32, max: u32) -> Result<u32, String> {
-  match divide_range(&divisions.chars().collect::<Vec<_>>(), min, max) {
-    (min, max) if min == max => Ok(min),
-    (min, max) => Err(format!("didn't arrive at one value: min = {}, max = {}", min, max)),
+impl Codec {
+  pub fn new(row_bits: u32, col_bits: u32, row_letters: &str, col_letters: &str) -> Result<Codec, String> {
+    // the seat map has a cell for every seat
+    if row_bits.saturating_add(col_bits) > MAX_SEAT_BITS {
+      return Err(format!(
+        "{} row and {} column bits make more than 2^{} seats",
+        row_bits, col_bits, MAX_SEAT_BITS
+      ));
+    }
+
+    Ok(Codec {
+      row_bits,
+      col_bits,
+      row_letters: parse_letters(row_letters)?,
+      col_letters: parse_letters(col_letters)?,
+    })
+  }
+
+  /// Creates a codec from the `PARAMS`.
+  fn from_params(params: &Params) -> Result<Codec, String> {
+    Codec::new(
+      params.get("row_bits"),
+      params.get("col_bits"),
+      params.get_str("row_letters"),
+      params.get_str("col_letters"),
+    )
+    .map_err(|error| format!("invalid plane geometry: {}", error))
+  }
+
+  pub fn rows(&self) -> u32 {
+    1 << self.row_bits
+  }
+
+  pub fn cols(&self) -> u32 {
+    1 << self.col_bits
   }
+
+  pub fn seat_id(&self, (row, col): (u32, u32)) -> u32 {
+    row * self.cols() + col
+  }
+
+  pub fn decode(&self, pass: &str) -> Result<(u32, u32), String> {
+    let letters = pass.chars().collect::<Vec<_>>();
+    let expected_len = (self.row_bits + self.col_bits) as usize;
+    if letters.len() != expected_len {
+      return Err(format!("expected {} letters, got {}", expected_len, letters.len()));
+    }
+
+    let (row, col) = letters.split_at(self.row_bits as usize);
+    Ok((
+      decode_bits(row, self.row_letters, 0)?,
+      decode_bits(col, self.col_letters, self.row_bits as usize)?,
+    ))
+  }
+
+  pub fn encode(&self, (row, col): (u32, u32)) -> Result<String, String> {
+    if row >= self.rows() || col >= self.cols() {
+      return Err(format!(
+        "seat ({}, {}) is outside of the {}x{} plane",
+        row,
+        col,
+        self.rows(),
+        self.cols()
+      ));
+    }
+
+    let mut pass = encode_bits(row, self.row_bits, self.row_letters);
+    pass.push_str(&encode_bits(col, self.col_bits, self.col_letters));
+    Ok(pass)
+  }
+}
+
+/// Taken seats of a plane, indexed by seat ID.
+#[derive(Debug)]
+struct SeatMap {
+  codec: Codec,
+  taken: Vec<bool>,
 }
 
-fn divide_range(divisions: &[char], min: u32, max: u32) -> (u32, u32) {
-  match divisions {
-    &[division, ref rest @ ..] => {
-      let half_of_elems_length = (max - min) / 2;
+/// A run of free seats, `first` and `last` are seat IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Gap {
+  first: u32,
+  last: u32,
+  kind: GapKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GapKind {
+  /// Free seats before the first taken one.
+  Front,
+  /// Free seats after the last taken one.
+  Back,
+  /// A free seat with taken seats on both sides.
+  Single,
+  /// Several free seats with taken seats on both sides.
+  Range,
+}
+
+impl SeatMap {
+  fn new(codec: Codec, seat_ids: &[u32]) -> SeatMap {
+    let mut taken = vec![false; (codec.rows() * codec.cols()) as usize];
+    for seat_id in seat_ids {
+      taken[*seat_id as usize] = true;
+    }
+
+    SeatMap { codec, taken }
+  }
+
+  fn render(&self) -> String {
+    let width = (self.codec.rows() - 1).to_string().len();
+
+    self
+      .taken
+      .chunks(self.codec.cols() as usize)
+      .enumerate()
+      .map(|(row, seats)| {
+        let seats = seats.iter().map(|taken| if *taken { '#' } else { '.' }).collect::<String>();
+        format!("{:>width$} {}", row, seats, width = width)
+      })
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
+
+  /// Returns all runs of free seats, from the front to the back.
+  /// Returns the single free seats between taken ones.
+  fn candidates(&self) -> Vec<Gap> {
+    self.gaps().into_iter().filter(|gap| gap.kind == GapKind::Single).collect()
+  }
+
+  fn gaps(&self) -> Vec<Gap> {
+    let mut gaps = vec![];
 
-      match division {
-        'F' | 'L' => divide_range(rest, min, min + half_of_elems_length),
-        'B' | 'R' => divide_range(rest, min + half_of_elems_length + 1, max),
-        _ => panic!("unexpected division {}", division),
+    let mut seat_id = 0;
+    while seat_id < self.taken.len() {
+      if self.taken[seat_id] {
+        seat_id += 1;
+        continue;
       }
+
+      let first = seat_id;
+      while seat_id < self.taken.len() && !self.taken[seat_id] {
+        seat_id += 1;
+      }
+      let last = seat_id - 1;
+
+      let kind = if first == 0 {
+        GapKind::Front
+      } else if seat_id == self.taken.len() {
+        GapKind::Back
+      } else if first == last {
+        GapKind::Single
+      } else {
+        GapKind::Range
+      };
+
+      gaps.push(Gap {
+        first: first as u32,
+        last: last as u32,
+        kind,
+      });
     }
-    &[] => (min, max),
+
+    gaps
   }
 }
 
+impl Gap {
+  fn describe(&self, codec: &Codec) -> String {
+    let seat = |seat_id: u32| {
+      let (row, col) = (seat_id / codec.cols(), seat_id % codec.cols());
+      match codec.encode((row, col)) {
+        Ok(pass) => format!("{} (row {}, col {}, {})", seat_id, row, col, pass),
+        Err(_) => seat_id.to_string(),
+      }
+    };
+
+    // rows which are completely free
+    let full_rows = self.first.div_ceil(codec.cols())..(self.last + 1) / codec.cols();
+
+    let reason = match self.kind {
+      GapKind::Front => "free at the front",
+      GapKind::Back => "free at the back",
+      GapKind::Single => "single free seat between taken ones, a candidate",
+      GapKind::Range => "free seats between taken ones",
+    };
+
+    let mut description = if self.first == self.last {
+      format!("seat {}: {}", seat(self.first), reason)
+    } else {
+      format!("seats {} to {}: {}", seat(self.first), seat(self.last), reason)
+    };
+    if !full_rows.is_empty() {
+      description += &format!(", rows {} to {} missing", full_rows.start, full_rows.end - 1);
+    }
+
+    description
+  }
+}
+
+/// Decodes `letters` as binary digits, `offset` is the position of the first letter in the pass.
+fn decode_bits(letters: &[char], [zero, one]: [char; 2], offset: usize) -> Result<u32, String> {
+  letters.iter().enumerate().try_fold(0, |value, (idx, letter)| match *letter {
+    letter if letter == zero => Ok(value << 1),
+    letter if letter == one => Ok(value << 1 | 1),
+    letter => Err(format!(
+      "unexpected letter {:?} at position {}, expected {:?} or {:?}",
+      letter,
+      offset + idx + 1,
+      zero,
+      one
+    )),
+  })
+}
+
+fn encode_bits(value: u32, bits: u32, [zero, one]: [char; 2]) -> String {
+  (0..bits)
+    .rev()
+    .map(|bit| if value >> bit & 1 == 1 { one } else { zero })
+    .collect()
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -81,7 +378,112 @@ mod tests {
 
   #[test]
   fn boarding_pass_decodes() {
-    assert_eq!(decode_boarding_pass("FBFBBFFRLR"), (44, 5))
+    assert_eq!(Codec::default().decode("FBFBBFFRLR"), Ok((44, 5)))
+  }
+
+  #[test]
+  fn codec_round_trips() {
+    let codec = Codec::default();
+    for pass in ["BFFFBBFRRR", "FFFBBBFRRR", "BBFFBBFRLL"].iter() {
+      assert_eq!(codec.encode(codec.decode(pass).unwrap()).as_deref(), Ok(*pass));
+    }
+    assert_eq!(codec.seat_id(codec.decode("BFFFBBFRRR").unwrap()), 567);
+
+    let small = Codec::new(3, 2, "01", "ab").unwrap();
+    assert_eq!(small.decode("101ba"), Ok((5, 2)));
+    assert_eq!(small.encode((5, 2)), Ok("101ba".to_string()));
+    assert_eq!(small.seat_id((5, 2)), 22);
+  }
+
+  #[test]
+  fn codec_errors_are_returned() {
+    let codec = Codec::default();
+
+    assert_eq!(codec.decode("FBFBBFFRL"), Err("expected 10 letters, got 9".to_string()));
+    assert_eq!(
+      codec.decode("FBFBBFFRXR"),
+      Err("unexpected letter 'X' at position 9, expected 'L' or 'R'".to_string())
+    );
+    assert_eq!(
+      codec.encode((128, 0)),
+      Err("seat (128, 0) is outside of the 128x8 plane".to_string())
+    );
+
+    assert!(Codec::new(7, 3, "FF", "LR").is_err());
+    assert!(Codec::new(7, 3, "FB", "L").is_err());
+    assert!(Codec::new(30, 3, "FB", "LR").is_err());
+    assert!(Codec::new(16, 15, "FB", "LR").is_err());
+    assert!(Codec::new(u32::MAX, 1, "FB", "LR").is_err());
+    assert!(Codec::new(12, 8, "FB", "LR").is_ok());
+  }
+
+  #[test]
+  fn gaps_are_found() {
+    let codec = Codec::new(2, 2, "FB", "LR").unwrap();
+    let seat_map = SeatMap::new(codec, &[5, 6, 8, 11, 12]);
+
+    assert_eq!(seat_map.render(), "0 ....\n1 .##.\n2 #..#\n3 #...");
+    assert_eq!(
+      seat_map.gaps(),
+      vec![
+        Gap {
+          first: 0,
+          last: 4,
+          kind: GapKind::Front
+        },
+        Gap {
+          first: 7,
+          last: 7,
+          kind: GapKind::Single
+        },
+        Gap {
+          first: 9,
+          last: 10,
+          kind: GapKind::Range
+        },
+        Gap {
+          first: 13,
+          last: 15,
+          kind: GapKind::Back
+        },
+      ]
+    );
+
+    let gaps = seat_map.gaps();
+    assert_eq!(
+      gaps[0].describe(&codec),
+      "seats 0 (row 0, col 0, FFLL) to 4 (row 1, col 0, FBLL): free at the front, rows 0 to 0 missing"
+    );
+    assert_eq!(
+      gaps[1].describe(&codec),
+      "seat 7 (row 1, col 3, FBRR): single free seat between taken ones, a candidate"
+    );
+  }
+
+  #[test]
+  fn ambiguous_seats_are_listed() {
+    let params = Params::resolve(
+      PARAMS,
+      &[
+        ("row_bits".to_string(), "2".to_string()),
+        ("col_bits".to_string(), "2".to_string()),
+      ],
+    )
+    .unwrap();
+
+    let solve = |input: &str| solve2_with(input, &params).map(|report| report.0);
+    assert_eq!(solve("FBLR FBRR\nBFLL BFLR"), Some("6".to_string()));
+    assert_eq!(
+      solve("FBLL FBRL\nBFLL BFRL"),
+      Some(
+        "3 candidate seats:
+  seat 5 (row 1, col 1, FBLR): single free seat between taken ones, a candidate
+  seat 7 (row 1, col 3, FBRR): single free seat between taken ones, a candidate
+  seat 9 (row 2, col 1, BFLR): single free seat between taken ones, a candidate"
+          .to_string()
+      )
+    );
+    assert_eq!(solve("FBLL FBLR"), None);
   }
 
   #[test]
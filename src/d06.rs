@@ -1,37 +1,190 @@
-use std::collections::HashSet;
+use crate::params::{Param, Params};
+use crate::report::Report;
 
-pub fn solve(input: &str) -> Option<Box<usize>> {
-  let sum_of_counts = input
-    .trim_end()
-    .split("\n\n")
-    .map(|group| group.chars().filter(|ch| *ch != '\n').collect::<HashSet<_>>().len())
-    .sum();
+pub const PARAMS: &[Param] = &[Param {
+  name: "query",
+  default: "any",
+  doc: "Which questions count per group: any, all, majority, atleast <k> or exactly <k>, optionally followed by except <person>, where persons are numbered from 1.",
+  check: |query| Query::parse(query).map(|_| ()),
+}];
 
-  Some(Box::new(sum_of_counts))
+pub const PARAMS2: &[Param] = &[Param {
+  name: "query",
+  default: "all",
+  doc: PARAMS[0].doc,
+  check: PARAMS[0].check,
+}];
+
+pub fn solve(input: &str) -> Option<Box<usize>> {
+  solve_with(input, &Params::defaults(PARAMS))
 }
 
 pub fn solve2(input: &str) -> Option<Box<usize>> {
-  let sum_of_counts = input
-    .trim_end()
-    .split("\n\n")
-    .map(|group| {
-      let answers_per_person = group
-        .split_ascii_whitespace()
-        .map(|person| person.chars().collect::<HashSet<_>>())
-        .collect::<Vec<_>>();
-
-      answers_per_person
-        .iter()
-        .fold(answers_per_person[0].clone(), |all_yes, persons_answers| {
-          all_yes.intersection(persons_answers).cloned().collect()
-        })
-        .len()
-    })
+  solve2_with(input, &Params::defaults(PARAMS2))
+}
+
+/// Sums the number of questions matching the query over all groups.
+pub fn solve_with(input: &str, params: &Params) -> Option<Box<usize>> {
+  let query = match Query::parse(params.get_str("query")) {
+    Ok(query) => query,
+    Err(error) => {
+      error!("{}", error);
+      return None;
+    }
+  };
+
+  let sum_of_counts = parse(input)
+    .iter()
+    .map(|group| query.matching(group).count_ones() as usize)
     .sum();
 
   Some(Box::new(sum_of_counts))
 }
 
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<usize>> {
+  solve_with(input, params)
+}
+
+/// Shows, for each question, the number of groups where it matches the query, and the number of persons answering it.
+pub fn histogram(input: &str, params: &Params) -> Option<Box<Report>> {
+  let query = match Query::parse(params.get_str("query")) {
+    Ok(query) => query,
+    Err(error) => {
+      error!("{}", error);
+      return None;
+    }
+  };
+  let groups = parse(input);
+
+  let mut lines = vec![format!("question  groups  persons  ({} groups)", groups.len())];
+  for question in 0..26 {
+    let bit = 1 << question;
+    let matching_groups = groups.iter().filter(|group| query.matching(group) & bit != 0).count();
+    let persons = groups.iter().flatten().filter(|answers| *answers & bit != 0).count();
+
+    lines.push(format!(
+      "{:>8}  {:>6}  {:>7}  {}",
+      (b'a' + question) as char,
+      matching_groups,
+      persons,
+      "#".repeat((matching_groups * 40).div_ceil(groups.len().max(1)))
+    ));
+  }
+
+  Some(Box::new(Report(lines.join("\n"))))
+}
+
+/// Groups of persons' answers, with question `a` being the lowest bit of the answers.
+fn parse(input: &str) -> Vec<Vec<u32>> {
+  let mut groups = vec![];
+  let mut group = vec![];
+
+  for line in input.lines().map(|line| line.trim()) {
+    if line.is_empty() {
+      if !group.is_empty() {
+        groups.push(group);
+        group = vec![];
+      }
+      continue;
+    }
+
+    let answers = line.chars().fold(0, |answers, question| match question {
+      'a'..='z' => answers | 1 << (question as u32 - 'a' as u32),
+      _ => panic!("unexpected question {:?} in {:?}", question, line),
+    });
+    group.push(answers);
+  }
+
+  if !group.is_empty() {
+    groups.push(group);
+  }
+
+  groups
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quantifier {
+  /// Anyone answered.
+  Any,
+  /// Everyone answered.
+  All,
+  /// More than half of the persons answered.
+  Majority,
+  AtLeast(usize),
+  Exactly(usize),
+}
+
+/// Selects questions by the number of persons in a group that answered them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Query {
+  quantifier: Quantifier,
+  /// Questions answered by these persons (numbered from 1) are excluded.
+  except: Vec<usize>,
+}
+
+impl Query {
+  /// Parses queries like `atleast 2 except 1`.
+  fn parse(query: &str) -> Result<Query, String> {
+    let usage = || {
+      format!(
+        "invalid query {:?}, expected any, all, majority, atleast <k> or exactly <k>, optionally followed by except <person>",
+        query
+      )
+    };
+    let number = |word: Option<&str>| word.and_then(|word| word.parse::<usize>().ok()).ok_or_else(usage);
+
+    let mut words = query.split_ascii_whitespace();
+    let quantifier = match words.next() {
+      Some("any") => Quantifier::Any,
+      Some("all") => Quantifier::All,
+      Some("majority") => Quantifier::Majority,
+      Some("atleast") => Quantifier::AtLeast(number(words.next())?),
+      Some("exactly") => Quantifier::Exactly(number(words.next())?),
+      _ => return Err(usage()),
+    };
+
+    let mut except = vec![];
+    while let Some(word) = words.next() {
+      match (word, number(words.next())?) {
+        ("except", person) if person > 0 => except.push(person),
+        _ => return Err(usage()),
+      }
+    }
+
+    Ok(Query { quantifier, except })
+  }
+
+  /// Returns questions of the group matching the query, as a bitset like the answers.
+  fn matching(&self, group: &[u32]) -> u32 {
+    let persons = group.len();
+
+    let mut matching = 0;
+    for question in 0..26 {
+      let bit = 1 << question;
+      let count = group.iter().filter(|answers| *answers & bit != 0).count();
+
+      let matches = match self.quantifier {
+        Quantifier::Any => count > 0,
+        Quantifier::All => count == persons,
+        Quantifier::Majority => count * 2 > persons,
+        Quantifier::AtLeast(k) => count >= k,
+        Quantifier::Exactly(k) => count == k,
+      };
+      if matches {
+        matching |= bit;
+      }
+    }
+
+    for person in self.except.iter() {
+      if let Some(answers) = group.get(person - 1) {
+        matching &= !answers;
+      }
+    }
+
+    matching
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -49,6 +202,45 @@ mod tests {
     assert_eq!(solve2(&input), Some(Box::new(6)));
   }
 
+  #[test]
+  fn queries_work() {
+    // a: 3 persons, b: 2, c: 1
+    let group = parse("abc\nab\na\n").remove(0);
+    let matching = |query: &str| {
+      let bits = Query::parse(query).unwrap().matching(&group);
+      (0..26)
+        .filter(|question| bits & 1 << question != 0)
+        .map(|question| (b'a' + question) as char)
+        .collect::<String>()
+    };
+
+    assert_eq!(matching("any"), "abc");
+    assert_eq!(matching("all"), "a");
+    assert_eq!(matching("majority"), "ab");
+    assert_eq!(matching("atleast 2"), "ab");
+    assert_eq!(matching("exactly 1"), "c");
+    assert_eq!(matching("exactly 0"), "defghijklmnopqrstuvwxyz");
+    assert_eq!(matching("any except 2"), "c");
+    assert_eq!(matching("any except 3 except 2"), "c");
+    assert_eq!(matching("any except 4"), "abc");
+
+    assert!(Query::parse("some").is_err());
+    assert!(Query::parse("atleast").is_err());
+    assert!(Query::parse("any except 0").is_err());
+    assert!(Query::parse("any but 1").is_err());
+  }
+
+  #[test]
+  fn histogram_works() {
+    let input = fs::read_to_string("inputs/sample06").unwrap();
+    let report = histogram(&input, &Params::defaults(PARAMS2)).unwrap().0;
+
+    let lines = report.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "question  groups  persons  (5 groups)");
+    assert_eq!(lines[1], "       a       3        8  ########################");
+    assert_eq!(lines[4], "       d       0        0  ");
+  }
+
   #[test]
   fn part_one_solved() {
     let input = fs::read_to_string("inputs/d06").unwrap();
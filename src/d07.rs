@@ -1,42 +1,306 @@
 use regex::Regex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
 use std::hash::Hash;
 
+use crate::json::Json;
+use crate::params::{any, parses, Param, Params};
+use crate::report::{Format, Report};
+
+pub const PARAMS: &[Param] = &[Param {
+  name: "color",
+  default: "shiny gold",
+  doc: "Color of your bag.",
+  check: any,
+}];
+
+pub const DOT_PARAMS: &[Param] = &[
+  Param {
+    name: "out",
+    default: "",
+    doc: "File to write the graph to, printed if empty.",
+    check: any,
+  },
+  Param {
+    name: "graph",
+    default: "contains",
+    doc: "Edge direction: contains (container -> contained) or contained_in (contained -> container).",
+    check: any,
+  },
+  Param {
+    name: "around",
+    default: "",
+    doc: "Only show this color with its ancestors or descendants, the whole graph if empty.",
+    check: any,
+  },
+  Param {
+    name: "restrict",
+    default: "descendants",
+    doc: "With around: descendants (bags inside) or ancestors (bags containing it).",
+    check: any,
+  },
+  Param {
+    name: "highlight",
+    default: "",
+    doc: "Highlight the shortest path between two colors, like \"shiny gold->dark blue\".",
+    check: any,
+  },
+];
+
+pub const QUERY_PARAMS: &[Param] = &[
+  Param {
+    name: "query",
+    default: "containers",
+    doc: "One of containers, contents, paths, depth or leaves.",
+    check: any,
+  },
+  Param {
+    name: "color",
+    default: "shiny gold",
+    doc: "Color to query, leaves of all bags if empty.",
+    check: any,
+  },
+  Param {
+    name: "to",
+    default: "",
+    doc: "With paths: the color to find paths to.",
+    check: any,
+  },
+  Param {
+    name: "depth",
+    default: "0",
+    doc: "With containers: how many levels up to look, 0 for no limit.",
+    check: parses::<usize>,
+  },
+];
+
+pub const VALIDATE_PARAMS: &[Param] = &[Param {
+  name: "format",
+  default: "text",
+  doc: "Output format: text or json.",
+  check: parses::<Format>,
+}];
+
 pub fn solve(input: &str) -> Option<Box<usize>> {
-  let contained_in = Rules::parse(input).contained_in();
-  let can_contain_shiny_gold = transitive_closure(&contained_in, &"shiny gold".to_string());
+  solve_with(input, &Params::defaults(PARAMS))
+}
+
+pub fn solve2(input: &str) -> Option<Box<u64>> {
+  solve2_with(input, &Params::defaults(PARAMS))
+}
+
+pub fn solve_with(input: &str, params: &Params) -> Option<Box<usize>> {
+  let rules = Rules::parse(input);
+  let color = params.get_str("color");
+  if !rules.check_known(color) {
+    return None;
+  }
+
+  let can_contain_color = transitive_closure(&rules.contained_in(), &color.to_string());
 
-  Some(Box::new(can_contain_shiny_gold.len()))
+  Some(Box::new(can_contain_color.len()))
 }
 
-pub fn solve2(input: &str) -> Option<Box<usize>> {
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<u64>> {
   let rules = Rules::parse(input);
+  let color = params.get_str("color");
+  if !rules.check_known(color) {
+    return None;
+  }
 
-  let mut to_satisfy = rules
-    .inner
-    .get(&"shiny gold".to_string())
-    .unwrap()
-    .iter()
-    .flat_map(|contained| to_n_strings(contained))
-    .collect::<Vec<_>>();
-  let mut count = 0;
+  match rules.count_contained(color) {
+    Ok(count) => Some(Box::new(count)),
+    Err(error) => {
+      error!("{}", error);
+      None
+    }
+  }
+}
 
-  while let Some(color) = to_satisfy.pop() {
-    count += 1;
+/// Renders the rule graph in Graphviz DOT format, with bag counts as edge labels.
+pub fn dot(input: &str, params: &Params) -> Option<Box<Report>> {
+  let rules = Rules::parse(input);
 
-    if let Some(all_contained) = rules.inner.get(&color) {
-      for contained in all_contained.iter() {
-        let mut items = to_n_strings(contained);
-        to_satisfy.append(&mut items);
+  let reversed = match params.get_str("graph") {
+    "contains" => false,
+    "contained_in" => true,
+    graph => {
+      error!("Unknown graph {:?}, expected contains or contained_in.", graph);
+      return None;
+    }
+  };
+
+  let around = params.get_str("around");
+  let nodes = if around.is_empty() {
+    None
+  } else {
+    if !rules.check_known(around) {
+      return None;
+    }
+
+    let around = around.to_string();
+    let mut nodes = match params.get_str("restrict") {
+      "descendants" => transitive_closure(&rules.contains(), &around),
+      "ancestors" => transitive_closure(&rules.contained_in(), &around),
+      restrict => {
+        error!("Unknown restriction {:?}, expected descendants or ancestors.", restrict);
+        return None;
+      }
+    };
+    nodes.insert(around);
+    Some(nodes)
+  };
+
+  let highlight = params.get_str("highlight");
+  let path = if highlight.is_empty() {
+    vec![]
+  } else {
+    let (from, to) = match highlight.split_once("->") {
+      Some((from, to)) => (from.trim(), to.trim()),
+      None => {
+        error!("Invalid highlight {:?}, expected <color>-><color>.", highlight);
+        return None;
       }
+    };
+    if !rules.check_known(from) || !rules.check_known(to) {
+      return None;
     }
+
+    match rules.shortest_path(from, to).or_else(|| rules.shortest_path(to, from)) {
+      Some(path) => path,
+      None => {
+        warn!("No path between {:?} and {:?} to highlight.", from, to);
+        vec![]
+      }
+    }
+  };
+
+  let graph = rules.subgraph(nodes.as_ref());
+  let dot = graph.to_dot(&path, reversed);
+  let out = params.get_str("out");
+  if out.is_empty() {
+    return Some(Box::new(Report(dot)));
   }
 
-  Some(Box::new(count))
+  match fs::write(out, dot) {
+    Ok(()) => Some(Box::new(Report(format!(
+      "Wrote {} bags and {} rules to {:?}.",
+      graph.colors.len(),
+      graph.edges.len(),
+      out
+    )))),
+    Err(error) => panic!("cannot write {:?}: {}", out, error),
+  }
 }
 
-fn to_n_strings(contained: &Contained) -> Vec<String> {
-  vec![contained.color.to_string(); contained.count]
+/// Answers questions about the bags around a color.
+pub fn query(input: &str, params: &Params) -> Option<Box<Report>> {
+  let rules = Rules::parse(input);
+  let color = params.get_str("color");
+  let query = params.get_str("query");
+  let to = params.get_str("to");
+
+  if (query != "leaves" || !color.is_empty()) && !rules.check_known(color) {
+    return None;
+  }
+  if query == "paths" && !rules.check_known(to) {
+    return None;
+  }
+
+  let mut lines = vec![];
+  match query {
+    "containers" => {
+      let max_depth = params.get::<usize>("depth");
+      let containers = rules.containers(color, max_depth);
+      for (container, depth) in containers.iter() {
+        lines.push(format!("  {:>3} {}", depth, container));
+      }
+      lines.push(format!("{} bags can contain {}.", containers.len(), color));
+    }
+    "contents" => match rules.contents(color) {
+      Ok(contents) => {
+        for (contained, count) in contents.iter() {
+          lines.push(format!("  {:>5} {}", count, contained));
+        }
+        let total = contents.iter().map(|(_, count)| count).sum::<u64>();
+        lines.push(format!("{} contains {} bags.", color, total));
+      }
+      Err(error) => {
+        error!("{}", error);
+        return None;
+      }
+    },
+    "paths" => {
+      let paths = rules.paths(color, to);
+      for path in paths.iter() {
+        lines.push(format!("  {}", path.join(" -> ")));
+      }
+      lines.push(format!("{} paths from {} to {}.", paths.len(), color, to));
+    }
+    "depth" => match rules.nesting_depth(color) {
+      Ok(depth) => lines.push(format!("Bags are nested {} levels deep inside {}.", depth, color)),
+      Err(error) => {
+        error!("{}", error);
+        return None;
+      }
+    },
+    "leaves" => {
+      let leaves = rules.leaves(if color.is_empty() { None } else { Some(color) });
+      for leaf in leaves.iter() {
+        lines.push(format!("  {}", leaf));
+      }
+      lines.push(format!("{} bags contain no other bags.", leaves.len()));
+    }
+    query => {
+      error!(
+        "Unknown query {:?}, expected containers, contents, paths, depth or leaves.",
+        query
+      );
+      return None;
+    }
+  }
+
+  Some(Box::new(Report(lines.join("\n"))))
+}
+
+/// Reports problems with the rules: unparsable lines, duplicates, undefined colors and cycles.
+pub fn validate(input: &str, params: &Params) -> Option<Box<Report>> {
+  let (rules, mut diagnostics) = Rules::parse_checked(input);
+  diagnostics.extend(rules.validate());
+
+  Some(Box::new(Report(match params.get::<Format>("format") {
+    Format::Text => {
+      let mut lines = diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect::<Vec<_>>();
+      lines.push(format!("{} rules, {} problems.", rules.inner.len(), diagnostics.len()));
+      lines.join("\n")
+    }
+    Format::Json => Json::object(vec![
+      ("rules", Json::from(rules.inner.len())),
+      (
+        "diagnostics",
+        Json::Array(diagnostics.iter().map(Diagnostic::to_json).collect()),
+      ),
+    ])
+    .to_string(),
+  })))
+}
+
+/// Levenshtein distance between `a` and `b`, by characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+  let b = b.chars().collect::<Vec<_>>();
+  let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+  for (i, a_char) in a.chars().enumerate() {
+    let mut current = vec![i + 1];
+    for (j, b_char) in b.iter().enumerate() {
+      let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+      current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+    }
+    previous = current;
+  }
+
+  previous[b.len()]
 }
 
 fn transitive_closure(contained_in: &ContainedIn, color: &Color) -> HashSet<Color> {
@@ -68,44 +332,516 @@ struct Contained {
   count: usize,
 }
 
+#[derive(Debug, PartialEq, Eq)]
+enum CountError {
+  /// Colors on the cycle, starting and ending with the same color.
+  Cycle(Vec<Color>),
+  /// The count doesn't fit into `u64` for the bag of this color.
+  Overflow(Color),
+}
+
+impl fmt::Display for CountError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CountError::Cycle(colors) => write!(f, "Bags contain themselves: {}.", colors.join(" -> ")),
+      CountError::Overflow(color) => write!(f, "Too many bags inside {:?} to count.", color),
+    }
+  }
+}
+
+/// A problem found while parsing or validating the rules, lines are numbered from 1.
+#[derive(Debug, PartialEq, Eq)]
+enum Diagnostic {
+  /// A line or a part of it that isn't a rule, it's skipped.
+  Unparsable { line: usize, fragment: String },
+  /// The color already has a rule, the one on `line` replaces it.
+  Duplicate { line: usize, color: Color, first_line: usize },
+  /// The color is inside a bag defined on `line`, but has no rule of its own.
+  Undefined { line: usize, color: Color },
+  SelfContainment { line: usize, color: Color },
+  /// Colors on the cycle, starting and ending with the same color.
+  Cycle(Vec<Color>),
+}
+
+impl Diagnostic {
+  fn kind(&self) -> &'static str {
+    match self {
+      Diagnostic::Unparsable { .. } => "unparsable",
+      Diagnostic::Duplicate { .. } => "duplicate",
+      Diagnostic::Undefined { .. } => "undefined",
+      Diagnostic::SelfContainment { .. } => "self_containment",
+      Diagnostic::Cycle(_) => "cycle",
+    }
+  }
+
+  fn to_json(&self) -> Json {
+    let mut fields = vec![("kind", Json::from(self.kind()))];
+    match self {
+      Diagnostic::Unparsable { line, fragment } => {
+        fields.push(("line", Json::from(*line)));
+        fields.push(("fragment", Json::from(fragment.as_str())));
+      }
+      Diagnostic::Duplicate {
+        line,
+        color,
+        first_line,
+      } => {
+        fields.push(("line", Json::from(*line)));
+        fields.push(("color", Json::from(color.as_str())));
+        fields.push(("first_line", Json::from(*first_line)));
+      }
+      Diagnostic::Undefined { line, color } | Diagnostic::SelfContainment { line, color } => {
+        fields.push(("line", Json::from(*line)));
+        fields.push(("color", Json::from(color.as_str())));
+      }
+      Diagnostic::Cycle(colors) => {
+        let colors = colors.iter().map(|color| Json::from(color.as_str())).collect();
+        fields.push(("colors", Json::Array(colors)));
+      }
+    }
+
+    Json::object(fields)
+  }
+}
+
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Diagnostic::Unparsable { line, fragment } => write!(f, "line {}: cannot parse {:?}", line, fragment),
+      Diagnostic::Duplicate {
+        line,
+        color,
+        first_line,
+      } => write!(f, "line {}: {:?} is already defined on line {}", line, color, first_line),
+      Diagnostic::Undefined { line, color } => write!(f, "line {}: {:?} has no rule", line, color),
+      Diagnostic::SelfContainment { line, color } => write!(f, "line {}: {:?} contains itself", line, color),
+      Diagnostic::Cycle(colors) => write!(f, "bags contain each other: {}", colors.join(" -> ")),
+    }
+  }
+}
+
 #[derive(Debug)]
 struct Rules {
   inner: HashMap<Color, Vec<Contained>>,
+  /// Line where each color is first defined.
+  lines: HashMap<Color, usize>,
 }
 
 impl Rules {
   fn parse(input: &str) -> Rules {
+    let (rules, diagnostics) = Rules::parse_checked(input);
+    for diagnostic in diagnostics.iter() {
+      warn!("{}", diagnostic);
+    }
+
+    rules
+  }
+
+  /// Parses the rules, collecting unparsable fragments and duplicates. Blank lines are skipped.
+  fn parse_checked(input: &str) -> (Rules, Vec<Diagnostic>) {
     lazy_static! {
-      static ref RE: Regex = Regex::new(r"(?P<count>\d+) (?P<color>.*) (bags|bag)\.?").unwrap();
+      static ref RE: Regex = Regex::new(r"^(?P<count>\d+) (?P<color>.+) bags?\.?$").unwrap();
     }
 
     let mut inner = HashMap::new();
-    for line in input.trim_end().split('\n') {
-      match &line.split(" bags contain ").collect::<Vec<_>>()[..] {
-        &[color, rules] => {
-          if rules == "no other bags." {
-            inner.insert(color.to_string(), vec![]);
-          } else {
-            for rule in rules.split(", ") {
-              if let Some(rule) = RE.captures_iter(rule).next() {
-                if let (Some(count), Some(contained_color)) = (rule.name("count"), rule.name("color")) {
-                  let contained = Contained {
-                    color: contained_color.as_str().to_string(),
-                    count: count.as_str().parse::<usize>().unwrap(),
-                  };
-
-                  let all_contained = inner.entry(color.to_string()).or_insert(vec![]);
-                  all_contained.push(contained);
-                }
-              }
-            }
-          }
+    let mut lines = HashMap::new();
+    let mut first_lines = HashMap::new();
+    let mut diagnostics = vec![];
+    for (idx, line) in input.lines().enumerate() {
+      let line_number = idx + 1;
+      if line.trim().is_empty() {
+        continue;
+      }
+
+      let (color, rules) = match line.split_once(" bags contain ") {
+        Some(rule) => rule,
+        None => {
+          diagnostics.push(Diagnostic::Unparsable {
+            line: line_number,
+            fragment: line.to_string(),
+          });
+          continue;
+        }
+      };
+
+      match first_lines.get(color) {
+        Some(first_line) => diagnostics.push(Diagnostic::Duplicate {
+          line: line_number,
+          color: color.to_string(),
+          first_line: *first_line,
+        }),
+        None => {
+          first_lines.insert(color.to_string(), line_number);
+        }
+      }
+
+      // the last rule for a color wins
+      lines.insert(color.to_string(), line_number);
+      let all_contained = inner.entry(color.to_string()).or_insert(vec![]);
+      all_contained.clear();
+      if rules == "no other bags." {
+        continue;
+      }
+
+      for rule in rules.split(", ") {
+        let contained = RE.captures(rule).and_then(|rule| {
+          Some(Contained {
+            color: rule["color"].to_string(),
+            count: rule["count"].parse::<usize>().ok()?,
+          })
+        });
+
+        match contained {
+          Some(contained) => all_contained.push(contained),
+          None => diagnostics.push(Diagnostic::Unparsable {
+            line: line_number,
+            fragment: rule.to_string(),
+          }),
+        }
+      }
+    }
+
+    (Rules { inner, lines }, diagnostics)
+  }
+
+  /// Finds colors without rules, bags containing themselves and cycles, in order of colors.
+  fn validate(&self) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    let mut undefined = HashSet::new();
+    for color in self.colors().into_iter().filter(|color| self.inner.contains_key(*color)) {
+      let line = self.lines[color];
+      for contained in self.inner[color].iter() {
+        if contained.color == *color {
+          diagnostics.push(Diagnostic::SelfContainment {
+            line,
+            color: color.clone(),
+          });
+        } else if !self.inner.contains_key(&contained.color) && undefined.insert(&contained.color) {
+          diagnostics.push(Diagnostic::Undefined {
+            line,
+            color: contained.color.clone(),
+          });
+        }
+      }
+    }
+
+    let mut finished = HashSet::new();
+    for color in self.colors() {
+      self.find_cycles(color, &mut finished, &mut vec![], &mut diagnostics);
+    }
+
+    diagnostics
+  }
+
+  /// Depth-first search reporting each edge back to a color on `path` as a cycle.
+  fn find_cycles<'a>(
+    &'a self,
+    color: &'a str,
+    finished: &mut HashSet<&'a str>,
+    path: &mut Vec<&'a str>,
+    diagnostics: &mut Vec<Diagnostic>,
+  ) {
+    if finished.contains(color) {
+      return;
+    }
+
+    path.push(color);
+    for contained in self.inner.get(color).into_iter().flatten() {
+      if contained.color == color {
+        // reported as self-containment
+        continue;
+      }
+
+      match path.iter().position(|on_path| *on_path == contained.color) {
+        Some(idx) => {
+          let mut cycle = path[idx..].iter().map(|color| color.to_string()).collect::<Vec<_>>();
+          cycle.push(contained.color.clone());
+          diagnostics.push(Diagnostic::Cycle(cycle));
+        }
+        None => self.find_cycles(&contained.color, finished, path, diagnostics),
+      }
+    }
+    path.pop();
+
+    finished.insert(color);
+  }
+
+  /// Returns all colors, either with a rule or inside another bag, sorted.
+  fn colors(&self) -> Vec<&Color> {
+    let mut colors = self
+      .inner
+      .iter()
+      .flat_map(|(container, all_contained)| {
+        std::iter::once(container).chain(all_contained.iter().map(|contained| &contained.color))
+      })
+      .collect::<Vec<_>>();
+    colors.sort();
+    colors.dedup();
+
+    colors
+  }
+
+  /// Returns the known color closest to `color`, if it's close enough to be a typo.
+  fn suggest(&self, color: &str) -> Option<&Color> {
+    self
+      .colors()
+      .into_iter()
+      .map(|known| (edit_distance(color, known), known))
+      .filter(|(distance, _)| *distance <= color.len() / 3 + 1)
+      .min()
+      .map(|(_, known)| known)
+  }
+
+  /// Logs an error with a suggestion if no rule mentions `color`.
+  fn check_known(&self, color: &str) -> bool {
+    if self.colors().iter().any(|known| *known == color) {
+      return true;
+    }
+
+    match self.suggest(color) {
+      Some(suggestion) => error!("Unknown color {:?}, did you mean {:?}?", color, suggestion),
+      None => error!("Unknown color {:?}.", color),
+    }
+    false
+  }
+
+  /// Returns colors that can contain `color` with the fewest levels of nesting between them,
+  /// looking at most `max_depth` levels up unless it's 0. Sorted by depth, then by color.
+  fn containers(&self, color: &str, max_depth: usize) -> Vec<(Color, usize)> {
+    let contained_in = self.contained_in();
+    let mut depths: HashMap<&str, usize> = HashMap::new();
+    let mut queue = VecDeque::from(vec![(color, 0)]);
+
+    while let Some((color, depth)) = queue.pop_front() {
+      if depth == max_depth && max_depth != 0 {
+        continue;
+      }
+
+      for container in contained_in.get(color).into_iter().flatten() {
+        if !depths.contains_key(container.as_str()) {
+          depths.insert(container, depth + 1);
+          queue.push_back((container, depth + 1));
         }
-        _ => panic!("cannot parse line: {}", line),
       }
     }
+    depths.remove(color);
+
+    let mut containers = depths
+      .into_iter()
+      .map(|(container, depth)| (container.to_string(), depth))
+      .collect::<Vec<_>>();
+    containers.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
 
-    Rules { inner }
+    containers
+  }
+
+  /// Returns how many bags of each color end up inside the bag of `color`,
+  /// sorted by count descending, then by color.
+  fn contents(&self, color: &str) -> Result<Vec<(Color, u64)>, CountError> {
+    self.count_contained(color)?;
+
+    // containers come before everything inside them
+    let mut order = vec![];
+    self.postorder(color, &mut HashSet::new(), &mut order);
+    order.reverse();
+
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+    counts.insert(color, 1);
+    for container in order {
+      let per_container = counts[container];
+      for contained in self.inner.get(container).into_iter().flatten() {
+        let count = counts.entry(&contained.color).or_insert(0);
+        *count = per_container
+          .checked_mul(contained.count as u64)
+          .and_then(|bags| bags.checked_add(*count))
+          .ok_or_else(|| CountError::Overflow(color.to_string()))?;
+      }
+    }
+    counts.remove(color);
+
+    let mut contents = counts
+      .into_iter()
+      .map(|(contained, count)| (contained.to_string(), count))
+      .collect::<Vec<_>>();
+    contents.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(contents)
+  }
+
+  fn postorder<'a>(&'a self, color: &'a str, visited: &mut HashSet<&'a str>, order: &mut Vec<&'a str>) {
+    if !visited.insert(color) {
+      return;
+    }
+
+    for contained in self.inner.get(color).into_iter().flatten() {
+      self.postorder(&contained.color, visited, order);
+    }
+    order.push(color);
+  }
+
+  /// Returns every chain of bags from `from` down to `to`, sorted.
+  fn paths(&self, from: &str, to: &str) -> Vec<Vec<Color>> {
+    let mut paths = vec![];
+    self.paths_inner(to, &mut vec![from], &mut paths);
+    paths.sort();
+
+    paths
+  }
+
+  fn paths_inner<'a>(&'a self, to: &str, path: &mut Vec<&'a str>, paths: &mut Vec<Vec<Color>>) {
+    let color = path[path.len() - 1];
+    if color == to {
+      paths.push(path.iter().map(|color| color.to_string()).collect());
+      return;
+    }
+
+    for contained in self.inner.get(color).into_iter().flatten() {
+      // skip cycles, the bags on them are already on the path
+      if !path.contains(&contained.color.as_str()) {
+        path.push(&contained.color);
+        self.paths_inner(to, path, paths);
+        path.pop();
+      }
+    }
+  }
+
+  /// Returns the longest chain of bags inside the bag of `color`, 0 if it's empty.
+  fn nesting_depth(&self, color: &str) -> Result<usize, CountError> {
+    self.count_contained(color)?;
+    Ok(self.nesting_depth_inner(color, &mut HashMap::new()))
+  }
+
+  fn nesting_depth_inner<'a>(&'a self, color: &'a str, depths: &mut HashMap<&'a str, usize>) -> usize {
+    if let Some(depth) = depths.get(color) {
+      return *depth;
+    }
+
+    let depth = self
+      .inner
+      .get(color)
+      .into_iter()
+      .flatten()
+      .map(|contained| self.nesting_depth_inner(&contained.color, depths) + 1)
+      .max()
+      .unwrap_or(0);
+    depths.insert(color, depth);
+
+    depth
+  }
+
+  /// Returns colors of bags that contain no other bags, only those inside `color` if given.
+  fn leaves(&self, color: Option<&str>) -> Vec<&Color> {
+    let inside = color.map(|color| transitive_closure(&self.contains(), &color.to_string()));
+
+    self
+      .colors()
+      .into_iter()
+      .filter(|color| self.inner.get(*color).is_none_or(|all_contained| all_contained.is_empty()))
+      .filter(|color| inside.as_ref().is_none_or(|inside| inside.contains(*color)))
+      .collect()
+  }
+
+  /// Counts bags inside the bag of `color`, computing the count once per color.
+  fn count_contained(&self, color: &str) -> Result<u64, CountError> {
+    self.count_contained_inner(color, &mut HashMap::new(), &mut vec![])
+  }
+
+  /// `path` holds the colors being counted, to detect cycles.
+  fn count_contained_inner<'a>(
+    &'a self,
+    color: &'a str,
+    counts: &mut HashMap<&'a str, u64>,
+    path: &mut Vec<&'a str>,
+  ) -> Result<u64, CountError> {
+    if let Some(count) = counts.get(color) {
+      return Ok(*count);
+    }
+
+    if let Some(idx) = path.iter().position(|on_path| *on_path == color) {
+      let mut cycle = path[idx..].iter().map(|color| color.to_string()).collect::<Vec<_>>();
+      cycle.push(color.to_string());
+      return Err(CountError::Cycle(cycle));
+    }
+
+    path.push(color);
+    let mut count: u64 = 0;
+    for contained in self.inner.get(color).into_iter().flatten() {
+      let inside = self.count_contained_inner(&contained.color, counts, path)?;
+
+      // the contained bags themselves, and everything inside them
+      count = inside
+        .checked_add(1)
+        .and_then(|per_bag| per_bag.checked_mul(contained.count as u64))
+        .and_then(|bags| bags.checked_add(count))
+        .ok_or_else(|| CountError::Overflow(color.to_string()))?;
+    }
+    path.pop();
+
+    counts.insert(color, count);
+    Ok(count)
+  }
+
+  /// Returns the colors directly inside bags of each color.
+  fn contains(&self) -> ContainedIn {
+    self
+      .inner
+      .iter()
+      .map(|(container, all_contained)| {
+        let colors = all_contained.iter().map(|contained| contained.color.clone()).collect();
+        (container.clone(), colors)
+      })
+      .collect()
+  }
+
+  /// Returns the colors from `from` to `to`, where each bag directly contains the next one.
+  fn shortest_path(&self, from: &str, to: &str) -> Option<Vec<Color>> {
+    let mut previous: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from(vec![from]);
+
+    while let Some(color) = queue.pop_front() {
+      if color == to {
+        let mut path = vec![to.to_string()];
+        let mut color = to;
+        while let Some(prev) = previous.get(color) {
+          path.push(prev.to_string());
+          color = prev;
+        }
+        path.reverse();
+
+        return Some(path);
+      }
+
+      for contained in self.inner.get(color).into_iter().flatten() {
+        if contained.color != from && !previous.contains_key(contained.color.as_str()) {
+          previous.insert(&contained.color, color);
+          queue.push_back(&contained.color);
+        }
+      }
+    }
+
+    None
+  }
+
+  /// Returns the colors and rules between them, limited to `nodes` if present.
+  fn subgraph(&self, nodes: Option<&HashSet<Color>>) -> Subgraph<'_> {
+    let included = |color: &str| nodes.is_none_or(|nodes| nodes.contains(color));
+
+    let colors = self
+      .colors()
+      .into_iter()
+      .filter(|color| included(color))
+      .collect::<Vec<_>>();
+    let edges = colors
+      .iter()
+      .flat_map(|container| {
+        let all_contained = self.inner.get(*container).into_iter().flatten();
+        all_contained.map(move |contained| (*container, contained))
+      })
+      .filter(|(_, contained)| included(&contained.color))
+      .collect();
+
+    Subgraph { colors, edges }
   }
 
   fn contained_in(&self) -> ContainedIn {
@@ -124,6 +860,55 @@ impl Rules {
   }
 }
 
+/// The part of the rules rendered as a DOT digraph.
+struct Subgraph<'a> {
+  colors: Vec<&'a Color>,
+  edges: Vec<(&'a Color, &'a Contained)>,
+}
+
+impl Subgraph<'_> {
+  /// Renders the subgraph as a DOT digraph, highlighting `path`.
+  ///
+  /// Edges go from containers to contained bags, or the other way around if `reversed`.
+  fn to_dot(&self, path: &[Color], reversed: bool) -> String {
+    let on_path = |from: &str, to: &str| path.windows(2).any(|pair| pair[0] == from && pair[1] == to);
+    let quote = |color: &str| format!("{:?}", color);
+
+    let mut lines = vec!["digraph bags {".to_string(), "  node [shape=box];".to_string()];
+    for color in self.colors.iter() {
+      let style = if path.contains(color) {
+        " [style=filled, fillcolor=gold]"
+      } else {
+        " []"
+      };
+      lines.push(format!("  {}{};", quote(color), style));
+    }
+
+    for (container, contained) in self.edges.iter() {
+      let (from, to) = if reversed {
+        (&contained.color, *container)
+      } else {
+        (*container, &contained.color)
+      };
+      let style = if on_path(container, &contained.color) {
+        ", color=red, penwidth=2"
+      } else {
+        ""
+      };
+      lines.push(format!(
+        "  {} -> {} [label=\"{}\"{}];",
+        quote(from),
+        quote(to),
+        contained.count,
+        style
+      ));
+    }
+    lines.push("}".to_string());
+
+    lines.join("\n") + "\n"
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -147,6 +932,218 @@ mod tests {
     assert_eq!(solve2(&puzzle_input), Some(Box::new(9569)));
   }
 
+  #[test]
+  fn counting_is_safe() {
+    let rules = Rules::parse(
+      "a bags contain 1000 b bags, 1000 c bags.
+b bags contain 1000 c bags.
+c bags contain 1000 d bags.
+d bags contain 1000 e bags.
+e bags contain no other bags.",
+    );
+    assert_eq!(rules.count_contained("e"), Ok(0));
+    assert_eq!(rules.count_contained("c"), Ok(1_001_000));
+    assert_eq!(rules.count_contained("a"), Ok(1_002_002_002_000));
+
+    let rules = Rules::parse(
+      "a bags contain 4294967296 b bags.
+b bags contain 4294967296 c bags.
+c bags contain no other bags.",
+    );
+    assert_eq!(rules.count_contained("b"), Ok(4294967296));
+    assert_eq!(rules.count_contained("a"), Err(CountError::Overflow("a".to_string())));
+
+    let rules = Rules::parse(
+      "a bags contain 1 b bag.
+b bags contain 2 c bags, 1 d bag.
+c bags contain no other bags.
+d bags contain 1 b bag.",
+    );
+    let error = rules.count_contained("a").unwrap_err();
+    assert_eq!(error.to_string(), "Bags contain themselves: b -> d -> b.");
+    let params = Params::resolve(PARAMS, &[("color".to_string(), "a".to_string())]).unwrap();
+    assert_eq!(solve2_with("a bags contain 1 a bag.", &params), None);
+  }
+
+  #[test]
+  fn dot_is_rendered() {
+    let sample_input = fs::read_to_string("inputs/sample07").unwrap();
+    let rules = Rules::parse(&sample_input);
+
+    let path = rules.shortest_path("light red", "dark olive").unwrap();
+    assert_eq!(path, vec!["light red", "bright white", "shiny gold", "dark olive"]);
+    assert_eq!(rules.shortest_path("dark olive", "light red"), None);
+
+    let mut nodes = transitive_closure(&rules.contains(), &"shiny gold".to_string());
+    nodes.insert("shiny gold".to_string());
+    assert_eq!(
+      rules.subgraph(Some(&nodes)).to_dot(&path, false),
+      r#"digraph bags {
+  node [shape=box];
+  "dark olive" [style=filled, fillcolor=gold];
+  "dotted black" [];
+  "faded blue" [];
+  "shiny gold" [style=filled, fillcolor=gold];
+  "vibrant plum" [];
+  "dark olive" -> "faded blue" [label="3"];
+  "dark olive" -> "dotted black" [label="4"];
+  "shiny gold" -> "dark olive" [label="1", color=red, penwidth=2];
+  "shiny gold" -> "vibrant plum" [label="2"];
+  "vibrant plum" -> "faded blue" [label="5"];
+  "vibrant plum" -> "dotted black" [label="6"];
+}
+"#
+    );
+
+    let graph = rules.subgraph(Some(&nodes));
+    assert_eq!((graph.colors.len(), graph.edges.len()), (5, 6));
+
+    let dot = rules.subgraph(None).to_dot(&[], true);
+    assert!(dot.contains(r#"  "bright white" -> "light red" [label="1"];"#));
+    assert_eq!(dot.matches(" -> ").count(), 13);
+
+    let printed = super::dot(&sample_input, &Params::defaults(DOT_PARAMS)).unwrap();
+    assert_eq!(printed.0, rules.subgraph(None).to_dot(&[], false));
+  }
+
+  #[test]
+  fn dot_params_are_validated() {
+    let sample_input = fs::read_to_string("inputs/sample07").unwrap();
+    let dot_with = |name: &str, value: &str| {
+      let params = Params::resolve(DOT_PARAMS, &[(name.to_string(), value.to_string())]).unwrap();
+      dot(&sample_input, &params)
+    };
+
+    assert!(dot_with("graph", "contained").is_none());
+    assert!(dot_with("around", "shiny golden").is_none());
+    assert!(dot_with("highlight", "light red").is_none());
+    assert!(dot_with("highlight", "light red -> shiny golden").is_none());
+  }
+
+  #[test]
+  fn queries_work() {
+    let sample_input = fs::read_to_string("inputs/sample07").unwrap();
+    let rules = Rules::parse(&sample_input);
+
+    assert_eq!(
+      rules.containers("shiny gold", 1),
+      vec![("bright white".to_string(), 1), ("muted yellow".to_string(), 1)]
+    );
+    assert_eq!(rules.containers("shiny gold", 0).len(), 4);
+    assert_eq!(rules.containers("light red", 0), vec![]);
+
+    let contents = rules.contents("shiny gold").unwrap();
+    assert_eq!(
+      contents,
+      vec![
+        ("dotted black".to_string(), 16),
+        ("faded blue".to_string(), 13),
+        ("vibrant plum".to_string(), 2),
+        ("dark olive".to_string(), 1),
+      ]
+    );
+    assert_eq!(contents.iter().map(|(_, count)| count).sum::<u64>(), 32);
+
+    assert_eq!(
+      rules.paths("light red", "shiny gold"),
+      vec![
+        vec!["light red", "bright white", "shiny gold"],
+        vec!["light red", "muted yellow", "shiny gold"],
+      ]
+    );
+    assert_eq!(rules.paths("light red", "faded blue").len(), 5);
+
+    assert_eq!(rules.nesting_depth("light red"), Ok(4));
+    assert_eq!(rules.nesting_depth("faded blue"), Ok(0));
+
+    assert_eq!(rules.leaves(None), vec!["dotted black", "faded blue"]);
+    assert_eq!(rules.leaves(Some("dark olive")), vec!["dotted black", "faded blue"]);
+    assert_eq!(rules.leaves(Some("faded blue")), Vec::<&Color>::new());
+  }
+
+  #[test]
+  fn unknown_colors_get_suggestions() {
+    assert_eq!(edit_distance("shiny gold", "shiny gold"), 0);
+    assert_eq!(edit_distance("shny gold", "shiny gold"), 1);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+
+    let sample_input = fs::read_to_string("inputs/sample07").unwrap();
+    let rules = Rules::parse(&sample_input);
+    assert_eq!(rules.suggest("shiny golden").map(|color| color.as_str()), Some("shiny gold"));
+    assert_eq!(rules.suggest("drak olive").map(|color| color.as_str()), Some("dark olive"));
+    assert_eq!(rules.suggest("mirrored teal"), None);
+    assert!(!rules.check_known("shiny golden"));
+
+    let params = Params::resolve(QUERY_PARAMS, &[("color".to_string(), "shiny golden".to_string())]).unwrap();
+    assert!(query(&sample_input, &params).is_none());
+  }
+
+  #[test]
+  fn rules_are_validated() {
+    let input = "a bags contain 1 b bag, 2 c bags.
+b bags contain 2 c bags.
+
+c bags contain 1 c bag, some e bags.
+b bags contain 3 d bags.
+d bags contain 1 a bag, 1 x bag.
+e bags have nothing
+";
+    let (rules, diagnostics) = Rules::parse_checked(input);
+    assert_eq!(
+      diagnostics,
+      vec![
+        Diagnostic::Unparsable {
+          line: 4,
+          fragment: "some e bags.".to_string(),
+        },
+        Diagnostic::Duplicate {
+          line: 5,
+          color: "b".to_string(),
+          first_line: 2,
+        },
+        Diagnostic::Unparsable {
+          line: 7,
+          fragment: "e bags have nothing".to_string(),
+        },
+      ]
+    );
+    assert_eq!(
+      rules.validate(),
+      vec![
+        Diagnostic::SelfContainment {
+          line: 4,
+          color: "c".to_string(),
+        },
+        Diagnostic::Undefined {
+          line: 6,
+          color: "x".to_string(),
+        },
+        Diagnostic::Cycle(vec!["a".to_string(), "b".to_string(), "d".to_string(), "a".to_string()]),
+      ]
+    );
+    assert_eq!(
+      diagnostics[1].to_string(),
+      "line 5: \"b\" is already defined on line 2"
+    );
+    assert_eq!(
+      rules.inner["b"],
+      vec![Contained {
+        color: "d".to_string(),
+        count: 3,
+      }]
+    );
+    assert_eq!(rules.lines["b"], 5);
+    assert_eq!(
+      Diagnostic::Cycle(vec!["a".to_string(), "a".to_string()]).to_json().to_string(),
+      r#"{"kind":"cycle","colors":["a","a"]}"#
+    );
+
+    let puzzle_input = fs::read_to_string("inputs/d07").unwrap();
+    let (rules, diagnostics) = Rules::parse_checked(&puzzle_input);
+    assert_eq!(diagnostics, vec![]);
+    assert_eq!(rules.validate(), vec![]);
+  }
+
   #[test]
   fn parser_works() {
     let sample_input = fs::read_to_string("inputs/sample07").unwrap();
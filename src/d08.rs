@@ -1,128 +1,1212 @@
-use std::collections::HashSet;
+use regex::Regex;
+use rustyline::Editor;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
 use std::hash::Hash;
 
+use crate::params::{any, parses, Param, Params};
+use crate::report::Report;
 use Op::*;
 
+pub const RUN_PARAMS: &[Param] = &[Param {
+  name: "limit",
+  default: "1000000",
+  doc: "Maximum number of instructions to execute.",
+  check: parses::<usize>,
+}];
+
+pub const CFG_PARAMS: &[Param] = &[Param {
+  name: "out",
+  default: "",
+  doc: "File to write the control-flow graph to in DOT format, nothing is written if empty.",
+  check: any,
+}];
+
 pub fn solve(input: &str) -> Option<Box<i64>> {
-  let mut machine = Machine::parse(&input);
+  let mut machine = Machine::parse(input).map_err(|error| error!("{}", error)).ok()?;
   machine.run_till_repetition();
 
   Some(Box::new(machine.acc))
 }
 
 pub fn solve2(input: &str) -> Option<Box<i64>> {
-  let machine = Machine::parse(&input);
+  let machine = Machine::parse(input).map_err(|error| error!("{}", error)).ok()?;
 
-  for (idx, Instr(op, arg)) in machine.program.iter().enumerate() {
-    match op {
-      Nop => {
-        if let Some(acc) = try_to_run_with_replaced_instr(&machine, idx, Instr(Jmp, *arg)) {
-          return Some(Box::new(acc));
+  machine.repair().map(|repair| Box::new(repair.acc))
+}
+
+/// Runs the program until it terminates, describing the registers and the output.
+pub fn run(input: &str, params: &Params) -> Option<Box<Report>> {
+  let mut machine = Machine::parse(input).map_err(|error| error!("{}", error)).ok()?;
+  let limit = params.get::<usize>("limit");
+
+  let mut steps = 0;
+  while !machine.terminated() && steps < limit {
+    machine.advance(false);
+    steps += 1;
+  }
+
+  let registers = REGISTERS
+    .iter()
+    .enumerate()
+    .map(|(idx, name)| format!("{} = {}", name, machine.reg(Reg(idx as u8))))
+    .collect::<Vec<_>>();
+  let output = machine.output.iter().map(|value| value.to_string()).collect::<Vec<_>>();
+
+  Some(Box::new(Report(format!(
+    "{} after {} steps: {}.\nOutput: {}",
+    if machine.terminated() {
+      "Terminated"
+    } else {
+      "Stopped"
+    },
+    steps,
+    registers.join(", "),
+    if output.is_empty() {
+      "none".to_string()
+    } else {
+      output.join(", ")
+    }
+  ))))
+}
+
+/// Summarizes the control-flow graph of the program, optionally writing it to a DOT file.
+pub fn cfg(input: &str, params: &Params) -> Option<Box<Report>> {
+  let machine = Machine::parse(input).map_err(|error| error!("{}", error)).ok()?;
+  let cfg = Cfg::build(&machine.program);
+  let mut summary = cfg.summary();
+
+  let out = params.get_str("out");
+  if !out.is_empty() {
+    match fs::write(out, cfg.to_dot(&machine.program)) {
+      Ok(()) => summary.push_str(&format!("\nWrote the graph to {:?}.", out)),
+      Err(error) => panic!("cannot write {:?}: {}", out, error),
+    }
+  }
+
+  Some(Box::new(Report(summary)))
+}
+
+/// Prints the program with labels in place of jump offsets.
+pub fn disassemble(input: &str) -> Option<Box<Report>> {
+  let machine = Machine::parse(input).map_err(|error| error!("{}", error)).ok()?;
+
+  Some(Box::new(Report(disassemble_program(&machine.program))))
+}
+
+/// Describes the instruction to flip for the program to terminate.
+pub fn repair(input: &str) -> Option<Box<Report>> {
+  let machine = Machine::parse(input).map_err(|error| error!("{}", error)).ok()?;
+  if !machine.is_classic() {
+    warn!("Only programs with acc, jmp and nop instructions can be repaired.");
+    return None;
+  }
+
+  let mut unpatched = machine.clone();
+  unpatched.run_till_repetition();
+  if unpatched.terminated() {
+    return Some(Box::new(Report(format!(
+      "The program already terminates, acc = {}.",
+      unpatched.acc
+    ))));
+  }
+
+  match machine.repair() {
+    Some(repair) => Some(Box::new(Report(format!(
+      "Patched instruction {}: {} -> {}, acc = {}. {}",
+      repair.idx,
+      machine.program[repair.idx],
+      repair.instr,
+      repair.acc,
+      if repair.unique {
+        "The fix is unique."
+      } else {
+        "Other flips fix the program too."
+      }
+    )))),
+    None => {
+      warn!("No single nop/jmp flip makes the program terminate.");
+      None
+    }
+  }
+}
+
+const DEBUG_HELP: &str = "Commands:
+  s, step [n]          execute n instructions, 1 by default
+  r, reverse [n]       undo n executed instructions, 1 by default
+  c, continue          run until a breakpoint, a watch, termination or a repeated instruction
+  b, break <ip|op>     stop before the instruction at ip, or before any instruction like jmp
+  d, delete [ip|op]    remove a breakpoint, or all of them
+  w, watch acc [<cmp> <n>]
+                       stop when acc changes, or when the comparison with ==, !=, <, <=, > or >= holds
+  unwatch              remove all watches
+  l, list [n]          print n instructions around ip, 3 by default
+  p, print             print ip, acc and the number of executed instructions
+  reset                start over, keeping breakpoints and watches
+  h, help              print this help
+  q, quit              leave the debugger";
+
+/// Runs an interactive debugger for the program until `quit` or end of input.
+pub fn debug(input: &str) -> Result<(), String> {
+  let machine = Machine::parse(input)?;
+  let mut debugger = Debugger::new(machine);
+  let mut rl = Editor::<()>::new();
+
+  println!("{} instructions loaded, type help for commands.", debugger.machine.program.len());
+  println!("{}", debugger.location());
+  while let Ok(line) = rl.readline("(d08) ") {
+    rl.add_history_entry(line.as_str());
+
+    let line = line.trim();
+    if line == "q" || line == "quit" {
+      break;
+    }
+
+    match debugger.execute(line) {
+      Ok(output) => println!("{}", output),
+      Err(error) => println!("{}", error),
+    }
+  }
+
+  Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Breakpoint {
+  Ip(i64),
+  /// Mnemonic of the instructions to stop at.
+  Op(&'static str),
+}
+
+impl Breakpoint {
+  fn parse(input: &str) -> Result<Breakpoint, String> {
+    if let Some(mnemonic) = Op::MNEMONICS.iter().find(|mnemonic| **mnemonic == input) {
+      return Ok(Breakpoint::Op(mnemonic));
+    }
+
+    match input.parse() {
+      Ok(ip) => Ok(Breakpoint::Ip(ip)),
+      Err(_) => Err(format!("Expected an ip or an instruction like jmp, got {:?}.", input)),
+    }
+  }
+}
+
+/// Stops the program when `acc` changes, or when it compares to a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Watch {
+  Changed,
+  Compare(&'static str, i64),
+}
+
+impl Watch {
+  const COMPARISONS: [&'static str; 6] = ["==", "!=", "<", "<=", ">", ">="];
+
+  fn parse(args: &[&str]) -> Result<Watch, String> {
+    match args {
+      ["acc"] => Ok(Watch::Changed),
+      ["acc", cmp, value] => {
+        let cmp = Watch::COMPARISONS
+          .iter()
+          .find(|known| *known == cmp)
+          .ok_or_else(|| format!("Unknown comparison {:?}.", cmp))?;
+        let value = value.parse().map_err(|_| format!("Expected a number, got {:?}.", value))?;
+        Ok(Watch::Compare(cmp, value))
+      }
+      _ => Err("Usage: watch acc [<cmp> <n>].".to_string()),
+    }
+  }
+
+  fn triggered(&self, before: i64, after: i64) -> bool {
+    match *self {
+      Watch::Changed => before != after,
+      Watch::Compare(cmp, value) => {
+        let holds = |acc: i64| match cmp {
+          "==" => acc == value,
+          "!=" => acc != value,
+          "<" => acc < value,
+          "<=" => acc <= value,
+          ">" => acc > value,
+          _ => acc >= value,
+        };
+        // only stop when the comparison starts to hold
+        holds(after) && !holds(before)
+      }
+    }
+  }
+}
+
+impl fmt::Display for Watch {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Watch::Changed => write!(f, "acc"),
+      Watch::Compare(cmp, value) => write!(f, "acc {} {}", cmp, value),
+    }
+  }
+}
+
+/// Steps through a `Machine`, recording its trace so that steps can be undone.
+struct Debugger {
+  machine: Machine,
+  breakpoints: Vec<Breakpoint>,
+  watches: Vec<Watch>,
+}
+
+impl Debugger {
+  fn new(machine: Machine) -> Debugger {
+    Debugger {
+      machine,
+      breakpoints: vec![],
+      watches: vec![],
+    }
+  }
+
+  /// Executes a debugger command, returning what to print.
+  fn execute(&mut self, line: &str) -> Result<String, String> {
+    let words = line.split_ascii_whitespace().collect::<Vec<_>>();
+    let count = |words: &[&str]| match words {
+      [] => Ok(1),
+      [count] => count.parse::<usize>().map_err(|_| format!("Expected a count, got {:?}.", count)),
+      _ => Err("Too many arguments.".to_string()),
+    };
+
+    match words.split_first() {
+      None => Ok(self.location()),
+      Some((&"s", args)) | Some((&"step", args)) => {
+        for _ in 0..count(args)? {
+          if self.machine.terminated() {
+            break;
+          }
+          self.machine.advance(true);
+        }
+        Ok(self.location())
+      }
+      Some((&"r", args)) | Some((&"reverse", args)) => {
+        for _ in 0..count(args)? {
+          if !self.machine.retreat() {
+            return Ok(format!("At the start of the program.\n{}", self.location()));
+          }
         }
+        Ok(self.location())
       }
-      Jmp => {
-        if let Some(acc) = try_to_run_with_replaced_instr(&machine, idx, Instr(Nop, *arg)) {
-          return Some(Box::new(acc));
+      Some((&"c", [])) | Some((&"continue", [])) => Ok(self.run()),
+      Some((&"b", [target])) | Some((&"break", [target])) => {
+        let breakpoint = Breakpoint::parse(target)?;
+        if !self.breakpoints.contains(&breakpoint) {
+          self.breakpoints.push(breakpoint);
         }
+        Ok(format!("Breakpoints: {}.", self.describe_breakpoints()))
+      }
+      Some((&"d", args)) | Some((&"delete", args)) => {
+        match args {
+          [] => self.breakpoints.clear(),
+          [target] => {
+            let breakpoint = Breakpoint::parse(target)?;
+            self.breakpoints.retain(|known| *known != breakpoint);
+          }
+          _ => return Err("Usage: delete [ip|op].".to_string()),
+        }
+        Ok(format!("Breakpoints: {}.", self.describe_breakpoints()))
+      }
+      Some((&"w", args)) | Some((&"watch", args)) => {
+        let watch = Watch::parse(args)?;
+        self.watches.push(watch);
+        Ok(format!("Watching {}.", watch))
+      }
+      Some((&"unwatch", [])) => {
+        self.watches.clear();
+        Ok("Removed all watches.".to_string())
+      }
+      Some((&"l", args)) | Some((&"list", args)) => {
+        let radius = match args {
+          [] => 3,
+          _ => count(args)?,
+        };
+        Ok(self.listing(radius))
       }
-      _ => (),
+      Some((&"p", [])) | Some((&"print", [])) => Ok(format!(
+        "ip = {}, acc = {}, {} instructions executed.",
+        self.machine.ip,
+        self.machine.acc,
+        self.machine.trace.len()
+      )),
+      Some((&"reset", [])) => {
+        self.machine.reset();
+        Ok(self.location())
+      }
+      Some((&"h", [])) | Some((&"help", [])) => Ok(DEBUG_HELP.to_string()),
+      Some((command, _)) => Err(format!("Cannot run {:?}, type help for commands.", command)),
     }
   }
 
-  None
-}
+  /// Runs until something stops the program, executing at least one instruction.
+  ///
+  /// Instructions already in the trace count as repeated, except for the first one.
+  fn run(&mut self) -> String {
+    let mut seen = self.machine.trace.iter().copied().collect::<HashSet<_>>();
+    let start = self.machine.trace.len();
+
+    loop {
+      if self.machine.terminated() {
+        return format!("Program terminated with acc = {}.", self.machine.acc);
+      }
+      let repeated = !seen.insert(self.machine.ip);
+      if repeated && self.machine.trace.len() > start {
+        return format!("Instruction {} would run a second time.\n{}", self.machine.ip, self.location());
+      }
+
+      let before = self.machine.acc;
+      self.machine.advance(true);
+
+      if let Some(watch) = self.watches.iter().find(|watch| watch.triggered(before, self.machine.acc)) {
+        return format!(
+          "Watch {}: acc changed from {} to {}.\n{}",
+          watch,
+          before,
+          self.machine.acc,
+          self.location()
+        );
+      }
+      if self.is_breakpoint(self.machine.ip) {
+        return format!("Breakpoint.\n{}", self.location());
+      }
+    }
+  }
 
-fn try_to_run_with_replaced_instr(machine: &Machine, idx: usize, instr: Instr) -> Option<i64> {
-  let mut new_machine = machine.clone();
-  new_machine.program[idx] = instr;
+  fn is_breakpoint(&self, ip: i64) -> bool {
+    let mnemonic = self.machine.instr(ip).map(|Instr(op, _)| op.mnemonic());
+    self.breakpoints.iter().any(|breakpoint| match breakpoint {
+      Breakpoint::Ip(at) => *at == ip,
+      Breakpoint::Op(kind) => Some(*kind) == mnemonic,
+    })
+  }
+
+  fn describe_breakpoints(&self) -> String {
+    if self.breakpoints.is_empty() {
+      return "none".to_string();
+    }
 
-  new_machine.run_till_repetition();
-  if new_machine.terminated() {
-    return Some(new_machine.acc);
+    let breakpoints = self.breakpoints.iter().map(|breakpoint| match breakpoint {
+      Breakpoint::Ip(ip) => ip.to_string(),
+      Breakpoint::Op(mnemonic) => mnemonic.to_string(),
+    });
+    breakpoints.collect::<Vec<_>>().join(", ")
+  }
+
+  /// Describes the next instruction and the accumulator.
+  fn location(&self) -> String {
+    match self.machine.instr(self.machine.ip) {
+      Some(instr) => format!("{:>4}: {:<8} acc = {}", self.machine.ip, instr.to_string(), self.machine.acc),
+      None => format!("Terminated at ip = {}, acc = {}.", self.machine.ip, self.machine.acc),
+    }
   }
 
-  None
+  /// Prints instructions within `radius` of ip, marking ip with `=>` and breakpoints with `*`.
+  fn listing(&self, radius: usize) -> String {
+    let ip = self.machine.ip;
+    let len = self.machine.program.len() as i64;
+    let radius = radius.min(self.machine.program.len()) as i64;
+    let first = ip.saturating_sub(radius).clamp(0, len);
+    let last = ip.saturating_add(radius + 1).clamp(0, len);
+
+    (first..last)
+      .map(|idx| {
+        format!(
+          "{}{} {:>4}: {}",
+          if idx == ip { "=>" } else { "  " },
+          if self.is_breakpoint(idx) { "*" } else { " " },
+          idx,
+          self.machine.program[idx as usize]
+        )
+      })
+      .collect::<Vec<_>>()
+      .join("\n")
+  }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Machine {
   acc: i64,
+  /// Registers `a` to `d`, `acc` is kept separately.
+  registers: [i64; 4],
   program: Vec<Instr>,
   ip: i64,
   trace: Vec<i64>,
+  /// Values written by `out` instructions.
+  output: Vec<i64>,
+}
+
+/// A run of instructions that are always executed together, from `start` to `end` exclusive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Block {
+  start: usize,
+  end: usize,
+  /// Indices of the blocks executed next, `None` if the program terminates.
+  successors: Vec<Option<usize>>,
+}
+
+/// Control-flow graph of a program, the first block is the entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cfg {
+  blocks: Vec<Block>,
+  /// Edges from a block back to a block it's reached from, each closes a loop.
+  back_edges: Vec<(usize, usize)>,
+  /// Blocks that can't be executed.
+  unreachable: Vec<usize>,
+  /// Blocks on a loop that can never reach termination once entered.
+  infinite: Vec<usize>,
+}
+
+impl Cfg {
+  fn build(program: &[Instr]) -> Cfg {
+    let len = program.len() as i64;
+    let in_program = |ip: i64| ip >= 0 && ip < len;
+
+    // blocks start at jump targets, and after jumps
+    let mut leaders = vec![false; program.len()];
+    if !program.is_empty() {
+      leaders[0] = true;
+    }
+    for (ip, instr) in program.iter().enumerate() {
+      let ip = ip as i64;
+      for next in instr.successors(ip, len) {
+        if next != ip + 1 && in_program(next) {
+          leaders[next as usize] = true;
+        }
+      }
+      if (instr.0.is_jump() || instr.0 == Halt) && in_program(ip + 1) {
+        leaders[ip as usize + 1] = true;
+      }
+    }
+
+    let starts = (0..program.len()).filter(|ip| leaders[*ip]).collect::<Vec<_>>();
+    let block_at = |ip: i64| {
+      if in_program(ip) {
+        Some(starts.partition_point(|start| *start <= ip as usize) - 1)
+      } else {
+        None
+      }
+    };
+
+    let blocks = starts
+      .iter()
+      .enumerate()
+      .map(|(idx, start)| {
+        let end = starts.get(idx + 1).copied().unwrap_or(program.len());
+        let last = end as i64 - 1;
+        let mut successors = program[last as usize]
+          .successors(last, len)
+          .into_iter()
+          .map(block_at)
+          .collect::<Vec<_>>();
+        successors.dedup();
+
+        Block {
+          start: *start,
+          end,
+          successors,
+        }
+      })
+      .collect::<Vec<_>>();
+
+    let mut cfg = Cfg {
+      blocks,
+      back_edges: vec![],
+      unreachable: vec![],
+      infinite: vec![],
+    };
+    cfg.analyze();
+
+    cfg
+  }
+
+  fn targets(&self, block: usize) -> impl Iterator<Item = usize> + '_ {
+    self.blocks[block].successors.iter().flatten().copied()
+  }
+
+  /// Finds back edges, unreachable blocks and blocks on infinite loops.
+  fn analyze(&mut self) {
+    let count = self.blocks.len();
+    if count == 0 {
+      return;
+    }
+
+    // iterative depth-first search from the entry, an edge to a block on the stack is a back edge
+    let mut reachable = vec![false; count];
+    let mut on_stack = vec![false; count];
+    let mut stack = vec![(0, self.targets(0).collect::<Vec<_>>())];
+    reachable[0] = true;
+    on_stack[0] = true;
+    while let Some((block, pending)) = stack.last_mut() {
+      let block = *block;
+      match pending.pop() {
+        Some(next) if on_stack[next] => self.back_edges.push((block, next)),
+        Some(next) if !reachable[next] => {
+          reachable[next] = true;
+          on_stack[next] = true;
+          let mut targets = self.targets(next).collect::<Vec<_>>();
+          targets.reverse();
+          stack.push((next, targets));
+        }
+        Some(_) => (),
+        None => {
+          on_stack[block] = false;
+          stack.pop();
+        }
+      }
+    }
+    self.back_edges.sort();
+    self.unreachable = (0..count).filter(|block| !reachable[*block]).collect();
+
+    // blocks that can terminate, walking edges backwards from the blocks that leave the program
+    let mut predecessors = vec![vec![]; count];
+    let mut terminates = vec![false; count];
+    let mut queue = VecDeque::new();
+    for (block, Block { successors, .. }) in self.blocks.iter().enumerate() {
+      for successor in successors.iter() {
+        match successor {
+          Some(next) => predecessors[*next].push(block),
+          None if !terminates[block] => {
+            terminates[block] = true;
+            queue.push_back(block);
+          }
+          None => (),
+        }
+      }
+    }
+    while let Some(block) = queue.pop_front() {
+      for &predecessor in predecessors[block].iter() {
+        if !terminates[predecessor] {
+          terminates[predecessor] = true;
+          queue.push_back(predecessor);
+        }
+      }
+    }
+
+    // a block is on a cycle if its strongly connected component has other blocks or it jumps to itself
+    let components = self.components(&predecessors);
+    let mut sizes = vec![0; count];
+    for component in components.iter() {
+      sizes[*component] += 1;
+    }
+    let on_cycle = |block: usize| sizes[components[block]] > 1 || self.targets(block).any(|next| next == block);
+
+    self.infinite = (0..count)
+      .filter(|block| reachable[*block] && !terminates[*block] && on_cycle(*block))
+      .collect();
+  }
+
+  /// Returns the strongly connected component of each block, numbered from 0.
+  ///
+  /// Uses Kosaraju's algorithm: blocks are ordered by when a depth-first search finishes them,
+  /// then the latest finished blocks collect their components walking edges backwards.
+  fn components(&self, predecessors: &[Vec<usize>]) -> Vec<usize> {
+    let count = self.blocks.len();
+
+    let mut finished = Vec::with_capacity(count);
+    let mut seen = vec![false; count];
+    for root in 0..count {
+      if seen[root] {
+        continue;
+      }
+
+      seen[root] = true;
+      let mut stack = vec![(root, self.targets(root).collect::<Vec<_>>())];
+      while let Some((block, pending)) = stack.last_mut() {
+        let block = *block;
+        match pending.pop() {
+          Some(next) if !seen[next] => {
+            seen[next] = true;
+            stack.push((next, self.targets(next).collect()));
+          }
+          Some(_) => (),
+          None => {
+            finished.push(block);
+            stack.pop();
+          }
+        }
+      }
+    }
+
+    let mut components = vec![usize::MAX; count];
+    let mut component = 0;
+    for &root in finished.iter().rev() {
+      if components[root] != usize::MAX {
+        continue;
+      }
+
+      components[root] = component;
+      let mut stack = vec![root];
+      while let Some(block) = stack.pop() {
+        for &predecessor in predecessors[block].iter() {
+          if components[predecessor] == usize::MAX {
+            components[predecessor] = component;
+            stack.push(predecessor);
+          }
+        }
+      }
+      component += 1;
+    }
+
+    components
+  }
+
+  /// Returns ips of the instructions on infinite loops.
+  fn infinite_ips(&self) -> Vec<usize> {
+    self
+      .infinite
+      .iter()
+      .flat_map(|block| self.blocks[*block].start..self.blocks[*block].end)
+      .collect::<Vec<_>>()
+  }
+
+  fn summary(&self) -> String {
+    let instructions = self.blocks.last().map_or(0, |block| block.end);
+    let mut lines = vec![format!("{} instructions in {} blocks.", instructions, self.blocks.len())];
+
+    for (idx, block) in self.blocks.iter().enumerate() {
+      let successors = block
+        .successors
+        .iter()
+        .map(|successor| match successor {
+          Some(next) => next.to_string(),
+          None => "exit".to_string(),
+        })
+        .collect::<Vec<_>>();
+
+      let mut line = format!(
+        "  block {}: ip {}..{} -> {}",
+        idx,
+        block.start,
+        block.end - 1,
+        successors.join(", ")
+      );
+      if self.unreachable.contains(&idx) {
+        line.push_str(", unreachable");
+      }
+      if self.infinite.contains(&idx) {
+        line.push_str(", infinite loop");
+      }
+      lines.push(line);
+    }
+
+    let loops = self
+      .back_edges
+      .iter()
+      .map(|(from, to)| format!("{} -> {}", from, to))
+      .collect::<Vec<_>>();
+    lines.push(format!(
+      "Loops: {}.",
+      if loops.is_empty() { "none".to_string() } else { loops.join(", ") }
+    ));
+
+    let infinite = self.infinite_ips().iter().map(|ip| ip.to_string()).collect::<Vec<_>>();
+    lines.push(if infinite.is_empty() {
+      "No infinite loops.".to_string()
+    } else {
+      format!("Infinite loop at ip {}.", infinite.join(", "))
+    });
+
+    lines.join("\n")
+  }
+
+  /// Renders the graph in DOT format, greying out unreachable blocks and marking infinite loops red.
+  fn to_dot(&self, program: &[Instr]) -> String {
+    let mut lines = vec![
+      "digraph cfg {".to_string(),
+      "  node [shape=box, fontname=monospace];".to_string(),
+    ];
+
+    for (idx, block) in self.blocks.iter().enumerate() {
+      let label = (block.start..block.end)
+        .map(|ip| format!("{}: {}\\l", ip, program[ip]))
+        .collect::<String>();
+      let style = if self.unreachable.contains(&idx) {
+        ", style=dashed, fontcolor=gray"
+      } else if self.infinite.contains(&idx) {
+        ", color=red"
+      } else {
+        ""
+      };
+      lines.push(format!("  b{} [label=\"{}\"{}];", idx, label, style));
+    }
+    lines.push("  exit [shape=doublecircle];".to_string());
+
+    for (idx, block) in self.blocks.iter().enumerate() {
+      let conditional = block.successors.len() > 1;
+      for (branch, successor) in block.successors.iter().enumerate() {
+        let to = match successor {
+          Some(next) => format!("b{}", next),
+          None => "exit".to_string(),
+        };
+
+        let mut attributes = vec![];
+        if conditional {
+          attributes.push(if branch == 0 { "label=\"else\"" } else { "label=\"then\"" });
+        }
+        if successor.is_some_and(|next| self.back_edges.contains(&(idx, next))) {
+          attributes.push("style=bold");
+        }
+
+        if attributes.is_empty() {
+          lines.push(format!("  b{} -> {};", idx, to));
+        } else {
+          lines.push(format!("  b{} -> {} [{}];", idx, to, attributes.join(", ")));
+        }
+      }
+    }
+    lines.push("}".to_string());
+
+    lines.join("\n") + "\n"
+  }
 }
 
+/// A flip of a single `nop` or `jmp` that makes the program terminate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Repair {
+  idx: usize,
+  /// The instruction replacing the one at `idx`.
+  instr: Instr,
+  /// Accumulator after the patched program terminates.
+  acc: i64,
+  /// `false` if flipping other instructions on the executed path works too.
+  unique: bool,
+}
+
+/// An instruction, the argument is an offset for jumps and a value for arithmetic.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 struct Instr(Op, i64);
 
+impl Instr {
+  /// Returns the instruction with `nop` and `jmp` swapped, `None` for other instructions.
+  fn flipped(&self) -> Option<Instr> {
+    match self {
+      Instr(Nop, arg) => Some(Instr(Jmp, *arg)),
+      Instr(Jmp, arg) => Some(Instr(Nop, *arg)),
+      _ => None,
+    }
+  }
+
+  /// Returns the ips that can be executed after this instruction at `ip`,
+  /// the fall-through one first. `len` is the length of the program.
+  fn successors(&self, ip: i64, len: i64) -> Vec<i64> {
+    match self {
+      Instr(Jz(_), arg) | Instr(Jnz(_), arg) if *arg != 1 => vec![ip + 1, ip.saturating_add(*arg)],
+      Instr(Halt, _) => vec![len],
+      _ => vec![self.next_ip(ip)],
+    }
+  }
+
+  /// Returns the ip executed after this instruction at `ip`, if it's not a conditional jump.
+  fn next_ip(&self, ip: i64) -> i64 {
+    match self {
+      Instr(Jmp, arg) => ip.saturating_add(*arg),
+      _ => ip.saturating_add(1),
+    }
+  }
+}
+
+/// Register names, `Reg` is an index into this list.
+const REGISTERS: [&str; 5] = ["acc", "a", "b", "c", "d"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+struct Reg(u8);
+
+impl Reg {
+  const ACC: Reg = Reg(0);
+
+  fn parse(name: &str) -> Result<Reg, String> {
+    match REGISTERS.iter().position(|register| *register == name) {
+      Some(idx) => Ok(Reg(idx as u8)),
+      None => Err(format!("unknown register {:?}, expected one of {}", name, REGISTERS.join(", "))),
+    }
+  }
+}
+
+impl fmt::Display for Reg {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", REGISTERS[self.0 as usize])
+  }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 enum Op {
+  /// Adds the argument to `acc`.
   Acc,
   Jmp,
   Nop,
+  /// Adds the argument to the register.
+  Add(Reg),
+  /// Adds the second register to the first one.
+  AddReg(Reg, Reg),
+  /// Multiplies the register by the argument.
+  Mul(Reg),
+  /// Multiplies the first register by the second one.
+  MulReg(Reg, Reg),
+  /// Sets the register to the argument.
+  Set(Reg),
+  /// Copies the first register into the second one.
+  Cpy(Reg, Reg),
+  /// Jumps if the register is zero.
+  Jz(Reg),
+  /// Jumps if the register is not zero.
+  Jnz(Reg),
+  /// Stops the program.
+  Halt,
+  /// Appends the register to the output.
+  Out(Reg),
 }
 
 impl Op {
-  fn parse(input: &str) -> Op {
-    match input {
-      "acc" => Acc,
-      "jmp" => Jmp,
-      "nop" => Nop,
-      _ => panic!("unexpected op: {}", input),
+  const MNEMONICS: [&'static str; 11] = [
+    "acc", "jmp", "nop", "add", "mul", "set", "cpy", "jz", "jnz", "halt", "out",
+  ];
+
+  fn mnemonic(&self) -> &'static str {
+    match self {
+      Acc => "acc",
+      Jmp => "jmp",
+      Nop => "nop",
+      Add(_) | AddReg(_, _) => "add",
+      Mul(_) | MulReg(_, _) => "mul",
+      Set(_) => "set",
+      Cpy(_, _) => "cpy",
+      Jz(_) => "jz",
+      Jnz(_) => "jnz",
+      Halt => "halt",
+      Out(_) => "out",
     }
   }
+
+  /// Returns `true` if the argument is an offset to jump by.
+  fn is_jump(&self) -> bool {
+    matches!(self, Jmp | Jz(_) | Jnz(_))
+  }
 }
 
-impl Machine {
-  fn parse(input: &str) -> Machine {
-    let mut program = vec![];
+impl fmt::Display for Op {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.mnemonic())
+  }
+}
 
-    for instr in input.trim_end().split("\n") {
-      match &instr.split_ascii_whitespace().collect::<Vec<_>>()[..] {
-        &[op, arg] => {
-          let op = Op::parse(op);
-          let arg = arg
-            .trim_start_matches("+")
-            .parse::<i64>()
-            .expect(&format!("expected arg, got {:?}", arg));
-          program.push(Instr(op, arg));
-        }
-        unexpected => panic!("unexpected line: {:?}", unexpected),
+impl fmt::Display for Instr {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let Instr(op, arg) = self;
+    match op {
+      Acc | Jmp | Nop => write!(f, "{} {:+}", op, arg),
+      Add(reg) | Jz(reg) | Jnz(reg) => write!(f, "{} {} {:+}", op, reg, arg),
+      Mul(reg) | Set(reg) => write!(f, "{} {} {}", op, reg, arg),
+      AddReg(to, from) | MulReg(to, from) => write!(f, "{} {} {}", op, to, from),
+      Cpy(from, to) => write!(f, "{} {} {}", op, from, to),
+      Halt => write!(f, "{}", op),
+      Out(reg) => write!(f, "{} {}", op, reg),
+    }
+  }
+}
+
+/// Assembles a program, one instruction per line.
+///
+/// Everything after `#` or `;` is a comment. Lines may start with a `label:`, which jumps
+/// can use as their target instead of an offset like `+3`.
+fn assemble(source: &str) -> Result<Vec<Instr>, String> {
+  lazy_static! {
+    static ref LABEL: Regex = Regex::new(r"^(?P<label>[A-Za-z_][A-Za-z0-9_]*):\s*").unwrap();
+  }
+
+  // labels are collected first, so that jumps can go forward
+  let mut labels = HashMap::new();
+  let mut lines = vec![];
+  for (idx, line) in source.lines().enumerate() {
+    let mut code = line.split(['#', ';']).next().unwrap_or("").trim();
+
+    if let Some(captures) = LABEL.captures(code) {
+      let label = captures["label"].to_string();
+      if labels.insert(label.clone(), lines.len() as i64).is_some() {
+        return Err(format!("line {}: label {:?} is already defined", idx + 1, label));
       }
+      code = &code[captures[0].len()..];
+    }
+
+    if !code.is_empty() {
+      lines.push((idx + 1, code));
     }
+  }
+
+  let mut program = vec![];
+  for (ip, (line_number, code)) in lines.into_iter().enumerate() {
+    let instr = assemble_instr(code, ip as i64, &labels).map_err(|error| format!("line {}: {}", line_number, error))?;
+    program.push(instr);
+  }
+
+  Ok(program)
+}
 
-    Machine {
+fn assemble_instr(code: &str, ip: i64, labels: &HashMap<String, i64>) -> Result<Instr, String> {
+  let number = |arg: &str| {
+    arg
+      .trim_start_matches('+')
+      .parse::<i64>()
+      .map_err(|_| format!("expected a number, got {:?}", arg))
+  };
+  let target = |arg: &str| match labels.get(arg) {
+    Some(label_ip) => Ok(label_ip - ip),
+    None if arg.starts_with(['+', '-']) || arg.starts_with(|ch: char| ch.is_ascii_digit()) => number(arg),
+    None => Err(format!("unknown label {:?}", arg)),
+  };
+
+  match code.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+    ["acc", arg] => Ok(Instr(Acc, number(arg)?)),
+    ["jmp", arg] => Ok(Instr(Jmp, target(arg)?)),
+    ["nop", arg] => Ok(Instr(Nop, target(arg)?)),
+    ["add", to, from] if REGISTERS.contains(&from) => Ok(Instr(AddReg(Reg::parse(to)?, Reg::parse(from)?), 0)),
+    ["add", reg, arg] => Ok(Instr(Add(Reg::parse(reg)?), number(arg)?)),
+    ["mul", to, from] if REGISTERS.contains(&from) => Ok(Instr(MulReg(Reg::parse(to)?, Reg::parse(from)?), 0)),
+    ["mul", reg, arg] => Ok(Instr(Mul(Reg::parse(reg)?), number(arg)?)),
+    ["set", reg, arg] => Ok(Instr(Set(Reg::parse(reg)?), number(arg)?)),
+    ["cpy", from, to] => Ok(Instr(Cpy(Reg::parse(from)?, Reg::parse(to)?), 0)),
+    ["jz", reg, arg] => Ok(Instr(Jz(Reg::parse(reg)?), target(arg)?)),
+    ["jnz", reg, arg] => Ok(Instr(Jnz(Reg::parse(reg)?), target(arg)?)),
+    ["halt"] => Ok(Instr(Halt, 0)),
+    ["out", reg] => Ok(Instr(Out(Reg::parse(reg)?), 0)),
+    [mnemonic, ..] if Op::MNEMONICS.contains(&mnemonic) => Err(format!("wrong arguments for {}", mnemonic)),
+    _ => Err(format!("cannot parse {:?}", code)),
+  }
+}
+
+/// Prints the program so that it can be assembled again, jumps within it go to `L<ip>` labels.
+fn disassemble_program(program: &[Instr]) -> String {
+  let len = program.len() as i64;
+  let targets = program
+    .iter()
+    .enumerate()
+    .filter(|(_, Instr(op, _))| op.is_jump())
+    .map(|(ip, Instr(_, arg))| (ip as i64).saturating_add(*arg))
+    .filter(|target| *target >= 0 && *target < len)
+    .collect::<HashSet<_>>();
+
+  let mut lines = vec![];
+  for (ip, instr) in program.iter().enumerate() {
+    let ip = ip as i64;
+    if targets.contains(&ip) {
+      lines.push(format!("L{}:", ip));
+    }
+
+    let Instr(op, arg) = instr;
+    let target = ip.saturating_add(*arg);
+    let code = if op.is_jump() && targets.contains(&target) {
+      match op {
+        Jz(reg) | Jnz(reg) => format!("{} {} L{}", op, reg, target),
+        _ => format!("{} L{}", op, target),
+      }
+    } else {
+      instr.to_string()
+    };
+    lines.push(format!("  {:<16}# {}", code, ip));
+  }
+
+  lines.join("\n")
+}
+
+impl Machine {
+  fn parse(input: &str) -> Result<Machine, String> {
+    let program = assemble(input)?;
+
+    Ok(Machine {
       acc: 0,
+      registers: [0; 4],
       ip: 0,
       trace: vec![],
+      output: vec![],
       program,
+    })
+  }
+
+  /// Returns `true` if the program only uses `acc`, `jmp` and `nop`.
+  fn is_classic(&self) -> bool {
+    self.program.iter().all(|Instr(op, _)| matches!(op, Acc | Jmp | Nop))
+  }
+
+  fn reg(&self, reg: Reg) -> i64 {
+    match reg {
+      Reg::ACC => self.acc,
+      Reg(idx) => self.registers[idx as usize - 1],
     }
   }
 
-  fn advance(&mut self, trace: bool) {
-    if trace {
-      self.trace.push(self.ip);
+  fn reg_mut(&mut self, reg: Reg) -> &mut i64 {
+    match reg {
+      Reg::ACC => &mut self.acc,
+      Reg(idx) => &mut self.registers[idx as usize - 1],
     }
+  }
 
+  /// Starts the program over.
+  fn reset(&mut self) {
+    self.acc = 0;
+    self.registers = [0; 4];
+    self.ip = 0;
+    self.trace.clear();
+    self.output.clear();
+  }
+
+  /// Executes one instruction, recording its ip in `trace` if asked to.
+  ///
+  /// Arithmetic wraps around on overflow, jumps out of the i64 range end up past the program.
+  fn advance(&mut self, trace: bool) {
     if !self.terminated() {
-      let Instr(op, arg) = self.program[self.ip as usize];
+      if trace {
+        self.trace.push(self.ip);
+      }
+
+      let instr = self.program[self.ip as usize];
+      let Instr(op, arg) = instr;
+      let mut next_ip = instr.next_ip(self.ip);
       match op {
-        Nop => self.ip += 1,
-        Jmp => self.ip += arg,
-        Acc => {
-          self.acc += arg;
-          self.ip += 1;
+        Acc => self.acc = self.acc.wrapping_add(arg),
+        Jmp | Nop => (),
+        Add(reg) => *self.reg_mut(reg) = self.reg(reg).wrapping_add(arg),
+        AddReg(to, from) => *self.reg_mut(to) = self.reg(to).wrapping_add(self.reg(from)),
+        Mul(reg) => *self.reg_mut(reg) = self.reg(reg).wrapping_mul(arg),
+        MulReg(to, from) => *self.reg_mut(to) = self.reg(to).wrapping_mul(self.reg(from)),
+        Set(reg) => *self.reg_mut(reg) = arg,
+        Cpy(from, to) => *self.reg_mut(to) = self.reg(from),
+        Jz(reg) if self.reg(reg) == 0 => next_ip = self.ip.saturating_add(arg),
+        Jnz(reg) if self.reg(reg) != 0 => next_ip = self.ip.saturating_add(arg),
+        Jz(_) | Jnz(_) => (),
+        Halt => next_ip = self.program.len() as i64,
+        Out(reg) => self.output.push(self.reg(reg)),
+      }
+      self.ip = next_ip;
+    }
+  }
+
+  /// Undoes the last instruction recorded in `trace`, returns `false` if there's none.
+  ///
+  /// Instructions overwriting a register can't be undone, the trace is replayed instead.
+  fn retreat(&mut self) -> bool {
+    let ip = match self.trace.last() {
+      Some(ip) => *ip,
+      None => return false,
+    };
+
+    match self.program[ip as usize] {
+      Instr(AddReg(_, _), _) | Instr(Mul(_), _) | Instr(MulReg(_, _), _) | Instr(Set(_), _) | Instr(Cpy(_, _), _) => {
+        let steps = self.trace.len() - 1;
+        self.reset();
+        for _ in 0..steps {
+          self.advance(true);
+        }
+      }
+      Instr(op, arg) => {
+        match op {
+          Acc => self.acc = self.acc.wrapping_sub(arg),
+          Add(reg) => *self.reg_mut(reg) = self.reg(reg).wrapping_sub(arg),
+          Out(_) => {
+            self.output.pop();
+          }
+          _ => (),
         }
+        self.trace.pop();
+        self.ip = ip;
       }
     }
+
+    true
+  }
+
+  fn instr(&self, ip: i64) -> Option<Instr> {
+    if ip < 0 {
+      None
+    } else {
+      self.program.get(ip as usize).copied()
+    }
+  }
+
+  /// Finds the instruction to flip in linear time.
+  ///
+  /// Instructions leading to termination are found by walking jumps backwards from
+  /// the end of the program. Flipping an instruction on the executed path fixes the program
+  /// if the flipped instruction continues at one of them, as the loop never reaches them.
+  ///
+  /// Programs that already terminate need no repair and get None, as do programs using
+  /// more than `acc`, `jmp` and `nop`, whose conditional jumps the walk doesn't follow.
+  fn repair(&self) -> Option<Repair> {
+    if !self.is_classic() {
+      warn!("Only programs with acc, jmp and nop instructions can be repaired.");
+      return None;
+    }
+
+    let len = self.program.len() as i64;
+    let in_program = |ip: i64| ip >= 0 && ip < len;
+
+    let mut predecessors = vec![vec![]; self.program.len()];
+    let mut terminating = vec![false; self.program.len()];
+    let mut queue = VecDeque::new();
+    for (idx, instr) in self.program.iter().enumerate() {
+      let next = instr.next_ip(idx as i64);
+      if in_program(next) {
+        predecessors[next as usize].push(idx);
+      } else {
+        terminating[idx] = true;
+        queue.push_back(idx);
+      }
+    }
+    while let Some(idx) = queue.pop_front() {
+      for &predecessor in predecessors[idx].iter() {
+        if !terminating[predecessor] {
+          terminating[predecessor] = true;
+          queue.push_back(predecessor);
+        }
+      }
+    }
+
+    let mut machine = self.clone();
+    machine.advance_till_repetition(true);
+    if machine.terminated() {
+      return None;
+    }
+
+    let fixes = machine
+      .trace
+      .iter()
+      .filter_map(|&ip| {
+        let instr = self.program[ip as usize].flipped()?;
+        let next = instr.next_ip(ip);
+        if !in_program(next) || terminating[next as usize] {
+          Some((ip as usize, instr))
+        } else {
+          None
+        }
+      })
+      .collect::<Vec<_>>();
+
+    let &(idx, instr) = fixes.first()?;
+    let mut patched = self.clone();
+    patched.program[idx] = instr;
+    patched.run_till_repetition();
+    if !patched.terminated() {
+      return None;
+    }
+
+    Some(Repair {
+      idx,
+      instr,
+      acc: patched.acc,
+      unique: fixes.len() == 1,
+    })
   }
 
   fn run_till_repetition(&mut self) {
+    self.advance_till_repetition(false);
+  }
+
+  fn advance_till_repetition(&mut self, trace: bool) {
     let mut seen = HashSet::new();
 
     while !seen.contains(&self.ip) {
       seen.insert(self.ip);
-      self.advance(false);
+      self.advance(trace);
     }
   }
 
@@ -159,10 +1243,125 @@ mod tests {
     assert_eq!(solve2(&input), Some(Box::new(1260)));
   }
 
+  /// Runs the machine with `instr` at `idx`, returns the accumulator if it terminates.
+  fn try_to_run_with_replaced_instr(machine: &Machine, idx: usize, instr: Instr) -> Option<i64> {
+    let mut new_machine = machine.clone();
+    new_machine.program[idx] = instr;
+
+    new_machine.run_till_repetition();
+    if new_machine.terminated() {
+      return Some(new_machine.acc);
+    }
+
+    None
+  }
+
+  #[test]
+  fn repair_agrees_with_brute_force() {
+    let input = fs::read_to_string("inputs/sample08").unwrap();
+    let machine = Machine::parse(&input).unwrap();
+    assert_eq!(
+      machine.repair(),
+      Some(Repair {
+        idx: 7,
+        instr: Instr(Nop, -4),
+        acc: 8,
+        unique: true,
+      })
+    );
+
+    let input = fs::read_to_string("inputs/d08").unwrap();
+    let machine = Machine::parse(&input).unwrap();
+    let fixes = (0..machine.program.len())
+      .filter_map(|idx| {
+        let instr = machine.program[idx].flipped()?;
+        try_to_run_with_replaced_instr(&machine, idx, instr).map(|acc| (idx, instr, acc))
+      })
+      .collect::<Vec<_>>();
+
+    let repair = machine.repair().unwrap();
+    assert_eq!(fixes, vec![(repair.idx, repair.instr, repair.acc)]);
+    assert!(repair.unique);
+
+    // flipping either instruction of the loop works, the first one executed is patched
+    let machine = Machine::parse("nop +3\njmp -1\nacc +3").unwrap();
+    let repair = machine.repair().unwrap();
+    assert_eq!((repair.idx, repair.instr, repair.acc, repair.unique), (0, Instr(Jmp, 3), 0, false));
+    assert_eq!(Machine::parse("jmp +1\njmp -1\njmp -1").unwrap().repair(), None);
+
+    // flipping either instruction of the loop reaches the last one, which loops forever on its own
+    let input = "set a 1\nnop +2\njmp -1\njnz a +0";
+    assert_eq!(Machine::parse(input).unwrap().repair(), None);
+    assert_eq!(solve2(input), None);
+
+    // nothing to repair, any flip could only break it
+    let input = "acc +1\nnop +0";
+    assert_eq!(Machine::parse(input).unwrap().repair(), None);
+    assert_eq!(solve2(input), None);
+    assert_eq!(
+      format!("{:?}", super::repair(input).unwrap()),
+      "The program already terminates, acc = 1."
+    );
+  }
+
+  #[test]
+  fn debugger_works() {
+    let input = fs::read_to_string("inputs/sample08").unwrap();
+    let mut debugger = Debugger::new(Machine::parse(&input).unwrap());
+
+    assert_eq!(debugger.execute("step 2"), Ok("   2: jmp +4   acc = 1".to_string()));
+    assert_eq!(debugger.execute("reverse"), Ok("   1: acc +1   acc = 0".to_string()));
+    assert_eq!(
+      debugger.execute("list 1"),
+      Ok("       0: nop +0\n=>     1: acc +1\n       2: jmp +4".to_string())
+    );
+    assert_eq!(
+      debugger.execute(&format!("list {}", usize::MAX)).map(|listing| listing.lines().count()),
+      Ok(9)
+    );
+
+    debugger.execute("break jmp").unwrap();
+    assert_eq!(debugger.execute("break 4"), Ok("Breakpoints: jmp, 4.".to_string()));
+    assert_eq!(debugger.execute("c"), Ok("Breakpoint.\n   2: jmp +4   acc = 1".to_string()));
+    assert_eq!(debugger.execute("delete jmp"), Ok("Breakpoints: 4.".to_string()));
+    assert_eq!(debugger.execute("c"), Ok("Breakpoint.\n   4: jmp -3   acc = 5".to_string()));
+    assert_eq!(debugger.execute("d"), Ok("Breakpoints: none.".to_string()));
+    assert_eq!(
+      debugger.execute("c"),
+      Ok("Instruction 1 would run a second time.\n   1: acc +1   acc = 5".to_string())
+    );
+
+    debugger.execute("reset").unwrap();
+    assert_eq!(debugger.execute("watch acc >= 2"), Ok("Watching acc >= 2.".to_string()));
+    assert_eq!(
+      debugger.execute("c"),
+      Ok("Watch acc >= 2: acc changed from 1 to 2.\n   7: jmp -4   acc = 2".to_string())
+    );
+    assert_eq!(debugger.execute("r 100"), Ok("At the start of the program.\n   0: nop +0   acc = 0".to_string()));
+    assert_eq!(
+      debugger.execute("p"),
+      Ok("ip = 0, acc = 0, 0 instructions executed.".to_string())
+    );
+
+    assert!(debugger.execute("break acc+1").is_err());
+    assert!(debugger.execute("watch acc ~ 1").is_err());
+    assert!(debugger.execute("jump").is_err());
+
+    // overflows and far jumps are commands like any other
+    let input = "set acc 9223372036854775807\nadd acc +1\njmp +9223372036854775807";
+    let mut debugger = Debugger::new(Machine::parse(input).unwrap());
+    assert_eq!(
+      debugger.execute("c"),
+      Ok("Program terminated with acc = -9223372036854775808.".to_string())
+    );
+    assert_eq!(debugger.execute("list"), Ok("".to_string()));
+    assert_eq!(debugger.execute("r 2"), Ok("   1: add acc +1 acc = 9223372036854775807".to_string()));
+  }
+
   #[test]
   fn parser_works() {
     let input = fs::read_to_string("inputs/sample08").unwrap();
-    let machine = Machine::parse(&input);
+    let machine = Machine::parse(&input).unwrap();
 
     assert_eq!(
       machine,
@@ -181,7 +1380,141 @@ mod tests {
         ],
         ip: 0,
         trace: vec![],
+        registers: [0; 4],
+        output: vec![],
       }
     );
   }
+
+  #[test]
+  fn assembler_works() {
+    let input = fs::read_to_string("inputs/sample08_isa").unwrap();
+    let mut machine = Machine::parse(&input).unwrap();
+    assert_eq!(
+      machine.program[..5],
+      [
+        Instr(Set(Reg(1)), 5),
+        Instr(Set(Reg::ACC), 1),
+        Instr(Jz(Reg(1)), 5),
+        Instr(Out(Reg(1)), 0),
+        Instr(MulReg(Reg::ACC, Reg(1)), 0),
+      ]
+    );
+
+    let output = run(&input, &Params::defaults(RUN_PARAMS)).unwrap();
+    assert_eq!(
+      output.0,
+      "Terminated after 30 steps: acc = 120, a = 0, b = 0, c = 0, d = 0.\nOutput: 5, 4, 3, 2, 1, 120"
+    );
+
+    while !machine.terminated() {
+      machine.advance(true);
+    }
+    assert_eq!(machine.output, vec![5, 4, 3, 2, 1, 120]);
+    while machine.retreat() {}
+    assert_eq!(machine, Machine::parse(&input).unwrap());
+
+    assert_eq!(
+      assemble("start: jmp end ; forward\n\n  # nothing here\nacc +1\nend: jnz b start"),
+      Ok(vec![Instr(Jmp, 2), Instr(Acc, 1), Instr(Jnz(Reg(2)), -2)])
+    );
+    assert_eq!(assemble("acc +1\njmp nowhere"), Err("line 2: unknown label \"nowhere\"".to_string()));
+    assert_eq!(assemble("x:\nx: halt"), Err("line 2: label \"x\" is already defined".to_string()));
+    assert_eq!(assemble("mul e 3"), Err("line 1: unknown register \"e\", expected one of acc, a, b, c, d".to_string()));
+    assert_eq!(assemble("halt 1"), Err("line 1: wrong arguments for halt".to_string()));
+    assert_eq!(assemble("acc one"), Err("line 1: expected a number, got \"one\"".to_string()));
+
+    // overflows wrap around and can be undone, far jumps leave the program
+    let input = "set a 9223372036854775807\nadd a +1\nmul a 2\nacc -9223372036854775808\nacc -1\njz a 9223372036854775807";
+    let mut machine = Machine::parse(input).unwrap();
+    while !machine.terminated() {
+      machine.advance(true);
+    }
+    assert_eq!((machine.acc, machine.registers[0]), (i64::MAX, 0));
+    assert_eq!(machine.ip, i64::MAX);
+    while machine.retreat() {}
+    assert_eq!(machine, Machine::parse(input).unwrap());
+  }
+
+  #[test]
+  fn cfg_works() {
+    let input = fs::read_to_string("inputs/sample08").unwrap();
+    let machine = Machine::parse(&input).unwrap();
+    let cfg = Cfg::build(&machine.program);
+
+    assert_eq!(
+      cfg.blocks.iter().map(|block| (block.start, block.end)).collect::<Vec<_>>(),
+      vec![(0, 1), (1, 3), (3, 5), (5, 6), (6, 8), (8, 9)]
+    );
+    assert_eq!(cfg.back_edges, vec![(2, 1)]);
+    assert_eq!(cfg.unreachable, vec![3, 5]);
+    assert_eq!(cfg.infinite_ips(), vec![1, 2, 3, 4, 6, 7]);
+    assert_eq!(
+      cfg.summary(),
+      "9 instructions in 6 blocks.
+  block 0: ip 0..0 -> 1
+  block 1: ip 1..2 -> 4, infinite loop
+  block 2: ip 3..4 -> 1, infinite loop
+  block 3: ip 5..5 -> 4, unreachable
+  block 4: ip 6..7 -> 2, infinite loop
+  block 5: ip 8..8 -> exit, unreachable
+Loops: 2 -> 1.
+Infinite loop at ip 1, 2, 3, 4, 6, 7."
+    );
+
+    // the loop can be left, so it isn't infinite
+    let input = fs::read_to_string("inputs/sample08_isa").unwrap();
+    let machine = Machine::parse(&input).unwrap();
+    let cfg = Cfg::build(&machine.program);
+    assert_eq!(cfg.blocks[1].successors, vec![Some(2), Some(3)]);
+    assert_eq!(cfg.blocks[3].successors, vec![None]);
+    assert_eq!(cfg.back_edges, vec![(2, 1)]);
+    assert_eq!(cfg.unreachable, vec![]);
+    assert_eq!(cfg.infinite, vec![]);
+
+    let dot = cfg.to_dot(&machine.program);
+    assert!(dot.contains("  b1 [label=\"2: jz a +5\\l\"];\n"));
+    assert!(dot.contains("  b1 -> b3 [label=\"then\"];\n"));
+    assert!(dot.contains("  b2 -> b1 [style=bold];\n"));
+    assert!(dot.contains("  b3 -> exit;\n"));
+
+    // a block jumping to itself is a loop, the block leading to it isn't part of it
+    let cfg = Cfg::build(&Machine::parse("acc +1\njmp +1\njmp +0").unwrap().program);
+    assert_eq!(cfg.blocks[1].successors, vec![Some(1)]);
+    assert_eq!(cfg.infinite, vec![1]);
+
+    // the patched program has no loops left
+    let input = fs::read_to_string("inputs/d08").unwrap();
+    let mut machine = Machine::parse(&input).unwrap();
+    assert!(!Cfg::build(&machine.program).infinite.is_empty());
+    let repair = machine.repair().unwrap();
+    machine.program[repair.idx] = repair.instr;
+    assert_eq!(Cfg::build(&machine.program).infinite, vec![]);
+  }
+
+  #[test]
+  fn disassembler_works() {
+    let input = fs::read_to_string("inputs/sample08").unwrap();
+    let machine = Machine::parse(&input).unwrap();
+    assert_eq!(
+      disassemble_program(&machine.program),
+      "  nop +0          # 0
+L1:
+  acc +1          # 1
+  jmp L6          # 2
+L3:
+  acc +3          # 3
+  jmp L1          # 4
+  acc -99         # 5
+L6:
+  acc +1          # 6
+  jmp L3          # 7
+  acc +6          # 8"
+    );
+
+    for path in ["inputs/d08", "inputs/sample08_isa"] {
+      let program = Machine::parse(&fs::read_to_string(path).unwrap()).unwrap().program;
+      assert_eq!(assemble(&disassemble_program(&program)), Ok(program));
+    }
+  }
 }
@@ -1,13 +1,30 @@
 use std::collections::HashSet;
 
+use crate::params::{parses, Param, Params};
+
+pub const PARAMS: &[Param] = &[Param {
+  name: "preamble",
+  default: "25",
+  doc: "Length of the preamble, and the number of previous numbers to consider.",
+  check: parses::<usize>,
+}];
+
 pub fn solve(input: &str) -> Option<Box<u64>> {
-  let numbers = parse(input);
-  first_non_conforming(numbers, 25).map(|res| Box::new(res))
+  solve_with(input, &Params::defaults(PARAMS))
 }
 
 pub fn solve2(input: &str) -> Option<Box<u64>> {
-  let weakness = 248131121;
+  solve2_with(input, &Params::defaults(PARAMS))
+}
+
+pub fn solve_with(input: &str, params: &Params) -> Option<Box<u64>> {
+  let numbers = parse(input);
+  first_non_conforming(numbers, params.get("preamble")).map(|res| Box::new(res))
+}
+
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<u64>> {
   let numbers = parse(input);
+  let weakness = first_non_conforming(numbers.clone(), params.get("preamble"))?;
   if let Some(region) = find_contagious_set_of_nums_that_sum_to(numbers, weakness) {
     if let (Some(largest), Some(smallest)) = (region.iter().max(), region.iter().min()) {
       return Some(Box::new(largest + smallest));
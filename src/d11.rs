@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::export::{Simulation, State};
+
 pub fn solve(input: &str) -> Option<Box<usize>> {
   solve_with_debug(input, false)
 }
@@ -17,27 +19,86 @@ pub fn solve2_debug(input: &str) -> Option<Box<usize>> {
 }
 
 fn solve_with_debug(input: &str, debug: bool) -> Option<Box<usize>> {
-  let mut layout = Layout::parse(input);
+  count_occupied(input, transition, debug)
+}
+
+/// All generations of the part one rules, till the layout stabilizes.
+pub fn simulation(input: &str) -> Simulation {
+  simulate(input, transition)
+}
+
+/// All generations of the part two rules, till the layout stabilizes.
+pub fn simulation2(input: &str) -> Simulation {
+  simulate(input, transition2)
+}
+
+const STATES: [State; 3] = [
+  State {
+    name: "floor",
+    color: [0x20, 0x20, 0x20],
+  },
+  State {
+    name: "empty",
+    color: [0x3C, 0xB4, 0x4B],
+  },
+  State {
+    name: "occupied",
+    color: [0xE6, 0x19, 0x4B],
+  },
+];
+
+fn simulate<F>(input: &str, transition: F) -> Simulation
+where
+  F: Fn(&Layout, Coords, &Cell) -> Cell + Copy,
+{
+  let layout = Layout::parse(input);
+  let mut generations = vec![layout.to_generation()];
+
+  settle(layout, transition, |layout| generations.push(layout.to_generation()));
+
+  Simulation {
+    states: &STATES,
+    generations,
+  }
+}
+
+fn count_occupied<F>(input: &str, transition: F, debug: bool) -> Option<Box<usize>>
+where
+  F: Fn(&Layout, Coords, &Cell) -> Cell + Copy,
+{
+  let layout = Layout::parse(input);
   if debug {
     layout.print();
   }
 
-  let mut prev_layout = None;
-
-  while prev_layout.is_none() || layout != prev_layout.unwrap() {
-    prev_layout = Some(layout.clone());
-    layout = layout.advance(transition);
-
+  let layout = settle(layout, transition, |layout| {
     if debug {
       layout.print();
     }
-  }
+  });
 
   Some(Box::new(
     layout.cells.values().filter(|cell| **cell == Occupied).count(),
   ))
 }
 
+/// Advances `layout` till it stabilizes, calling `on_advance` with each changed layout.
+fn settle<F, G>(mut layout: Layout, transition: F, mut on_advance: G) -> Layout
+where
+  F: Fn(&Layout, Coords, &Cell) -> Cell + Copy,
+  G: FnMut(&Layout),
+{
+  loop {
+    let next_layout = layout.advance(transition);
+    if next_layout == layout {
+      return layout;
+    }
+
+    on_advance(&next_layout);
+    layout = next_layout;
+  }
+}
+
 fn transition(layout: &Layout, coords: Coords, cell: &Cell) -> Cell {
   let occupied_neigbhors = layout.occupied_neighbors(coords);
 
@@ -61,25 +122,7 @@ fn transition(layout: &Layout, coords: Coords, cell: &Cell) -> Cell {
 }
 
 fn solve2_with_debug(input: &str, debug: bool) -> Option<Box<usize>> {
-  let mut layout = Layout::parse(input);
-  if debug {
-    layout.print();
-  }
-
-  let mut prev_layout = None;
-
-  while prev_layout.is_none() || layout != prev_layout.unwrap() {
-    prev_layout = Some(layout.clone());
-    layout = layout.advance(transition2);
-
-    if debug {
-      layout.print();
-    }
-  }
-
-  Some(Box::new(
-    layout.cells.values().filter(|cell| **cell == Occupied).count(),
-  ))
+  count_occupied(input, transition2, debug)
 }
 
 fn transition2(layout: &Layout, coords: Coords, cell: &Cell) -> Cell {
@@ -229,7 +272,25 @@ impl Layout {
     }
   }
 
+  fn to_generation(&self) -> Vec<((i64, i64), usize)> {
+    self
+      .cells
+      .iter()
+      .map(|(&(row_idx, col_idx), cell)| {
+        let state = match cell {
+          Floor => 0,
+          Empty => 1,
+          Occupied => 2,
+        };
+
+        ((row_idx as i64, col_idx as i64), state)
+      })
+      .collect()
+  }
+
   fn print(&self) {
+    let mut image = String::new();
+
     for row_idx in 0..self.height {
       for col_idx in 0..self.width {
         let ch = match self.cells.get(&(row_idx, col_idx)).unwrap() {
@@ -238,12 +299,12 @@ impl Layout {
           Floor => '.',
         };
 
-        print!("{}", ch);
+        image.push(ch);
       }
-      println!();
+      image.push('\n');
     }
 
-    println!();
+    info!("{}", image);
   }
 }
 
@@ -2,12 +2,36 @@ use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
 use fnv::{FnvHasher, FnvBuildHasher};
 
+use crate::params::{parses, Param, Params};
+
+pub const PARAMS: &[Param] = &[Param {
+  name: "turns",
+  default: "2020",
+  doc: "Number of turns to play.",
+  check: parses::<usize>,
+}];
+
+pub const PARAMS2: &[Param] = &[Param {
+  name: "turns",
+  default: "30000000",
+  doc: PARAMS[0].doc,
+  check: PARAMS[0].check,
+}];
+
 pub fn solve(input: &str) -> Option<Box<u64>> {
-  Some(Box::new(last_number_spoken2(input, 2020)))
+  solve_with(input, &Params::defaults(PARAMS))
 }
 
 pub fn solve2(input: &str) -> Option<Box<u64>> {
-  Some(Box::new(last_number_spoken2(input, 30000000)))
+  solve2_with(input, &Params::defaults(PARAMS2))
+}
+
+pub fn solve_with(input: &str, params: &Params) -> Option<Box<u64>> {
+  Some(Box::new(last_number_spoken2(input, params.get("turns"))))
+}
+
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<u64>> {
+  solve_with(input, params)
 }
 
 fn last_number_spoken2(input: &str, till_turn: usize) -> u64 {
@@ -26,10 +26,10 @@ pub fn solve2(input: &str) -> Option<Box<u64>> {
       let valid_tickets = valid_tickets.clone();
 
       thread::spawn(move || {
-        println!("Running thread {}...", idx);
+        debug!("Running thread {}...", idx);
         match task.field_to_idx(&valid_tickets) {
           Some(assignment) => {
-            println!("Thread {} found assignment {:?}", idx, &assignment);
+            debug!("Thread {} found assignment {:?}", idx, &assignment);
             sender.send(assignment).unwrap_or(());
           }
           _ => (),
@@ -51,7 +51,7 @@ pub fn solve2(input: &str) -> Option<Box<u64>> {
       Some(Box::new(result))
     }
     Err(err) => {
-      dbg!(err);
+      error!("No thread has found an assignment: {:?}", err);
       None
     }
   }
@@ -1,26 +1,80 @@
 use std::collections::HashSet;
 use std::ops::RangeInclusive;
 
+use crate::export::{Simulation, State};
+use crate::params::{parses, Param, Params};
+
+pub const PARAMS: &[Param] = &[Param {
+  name: "cycles",
+  default: "6",
+  doc: "Number of boot process cycles.",
+  check: parses::<usize>,
+}];
+
 pub fn solve(input: &str) -> Option<Box<usize>> {
+  solve_with(input, &Params::defaults(PARAMS))
+}
+
+pub fn solve2(input: &str) -> Option<Box<usize>> {
+  solve2_with(input, &Params::defaults(PARAMS))
+}
+
+pub fn solve_with(input: &str, params: &Params) -> Option<Box<usize>> {
   let mut cube = Cube::parse(input, 3);
 
-  for _turn in 0..6 {
+  for _turn in 0..params.get::<usize>("cycles") {
     cube = cube.advance();
   }
 
   Some(Box::new(cube.active.len()))
 }
 
-pub fn solve2(input: &str) -> Option<Box<usize>> {
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<usize>> {
   let mut cube = Cube::parse(input, 4);
 
-  for _turn in 0..6 {
+  for _turn in 0..params.get::<usize>("cycles") {
     cube = cube.advance();
   }
 
   Some(Box::new(cube.active.len()))
 }
 
+/// Evolution of the `z = 0` slice of the 3-dimensional cube.
+pub fn simulation(input: &str, params: &Params) -> Simulation {
+  simulate(input, 3, params.get("cycles"))
+}
+
+/// Evolution of the `z = 0, w = 0` slice of the 4-dimensional cube.
+pub fn simulation2(input: &str, params: &Params) -> Simulation {
+  simulate(input, 4, params.get("cycles"))
+}
+
+const STATES: [State; 2] = [
+  State {
+    name: "inactive",
+    color: [0x10, 0x10, 0x30],
+  },
+  State {
+    name: "active",
+    color: [0xFF, 0xE1, 0x19],
+  },
+];
+
+fn simulate(input: &str, dimensions: usize, cycles: usize) -> Simulation {
+  let mut cube = Cube::parse(input, dimensions);
+  let mut generations = vec![cube.slice()];
+
+  for _turn in 0..cycles {
+    cube = cube.advance();
+    generations.push(cube.slice());
+  }
+
+  Simulation {
+    states: &STATES,
+    generations,
+  }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Point {
   coords: Vec<i64>,
@@ -94,6 +148,16 @@ impl Cube {
     self.active.insert(point);
   }
 
+  /// Active cells with all coordinates except `x` and `y` equal to zero, as `((y, x), 1)`.
+  fn slice(&self) -> Vec<((i64, i64), usize)> {
+    self
+      .active
+      .iter()
+      .filter(|point| point.coords[2..].iter().all(|coord| *coord == 0))
+      .map(|point| ((point.coords[1], point.coords[0]), 1))
+      .collect()
+  }
+
   fn is_bordering_or_inside(&self, point: &Point) -> bool {
     (0..self.dimensions).into_iter().all(|dimension_id| {
       let dimension_border = &self.border[dimension_id];
@@ -162,8 +226,8 @@ pub fn go_bananas(input: &str) -> Option<Box<()>> {
 
   for turn in 0..5 {
     cube = cube.advance();
-    println!(
-      "{}-dimensional cube has {} active cells at turn {}.\nBorders: {:?}.\n",
+    info!(
+      "{}-dimensional cube has {} active cells at turn {}.\nBorders: {:?}.",
       cube.dimensions,
       cube.active.len(),
       turn + 1,
@@ -122,9 +122,7 @@ fn remove_edges(tile_rows: Vec<String>) -> Vec<String> {
 }
 
 pub fn show_image(image: &Vec<String>) {
-  for row in image.iter() {
-    println!("{}", row);
-  }
+  debug!("{}", image.join("\n"));
 }
 
 fn backtrack(tiles: &TilesMap) -> Option<BacktrackAssignment> {
@@ -1,7 +1,28 @@
+use crate::params::{parses, Param, Params};
+
+pub const PARAMS: &[Param] = &[Param {
+  name: "moves",
+  default: "100",
+  doc: "Number of moves the crab makes.",
+  check: parses::<usize>,
+}];
+
+pub const PARAMS2: &[Param] = &[Param {
+  name: "moves",
+  default: "10000000",
+  doc: PARAMS[0].doc,
+  check: PARAMS[0].check,
+}];
+
 pub fn solve(input: &str) -> Option<Box<String>> {
+  solve_with(input, &Params::defaults(PARAMS))
+}
+
+#[allow(clippy::box_collection)] // solvers return `Option<Box<T>>`
+pub fn solve_with(input: &str, params: &Params) -> Option<Box<String>> {
   let mut cups = Ring::parse(input);
 
-  for _ in 0..100 {
+  for _ in 0..params.get::<usize>("moves") {
     cups.execute_move();
   }
 
@@ -34,6 +55,11 @@ const TOTAL_CUPS: usize = 1_000_000;
 static mut RING: [usize; TOTAL_CUPS + 1] = [0; TOTAL_CUPS + 1];
 
 pub fn solve2(input: &str) -> Option<Box<usize>> {
+  solve2_with(input, &Params::defaults(PARAMS2))
+}
+
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<usize>> {
+  let moves = params.get::<usize>("moves");
   let cups = input
     .trim_end()
     .chars()
@@ -67,7 +93,7 @@ pub fn solve2(input: &str) -> Option<Box<usize>> {
     // adds reference from the tail to the head
     RING[TOTAL_CUPS] = RING[0];
 
-    for _round in 0..10_000_000 {
+    for _round in 0..moves {
       // pick up three cups
       let pick1 = RING[RING[0]];
       let pick2 = RING[pick1];
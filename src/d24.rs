@@ -1,5 +1,8 @@
 use std::collections::HashMap;
 
+use crate::export::{Simulation, State};
+use crate::params::{parses, Param, Params};
+
 // Cool reference about hexagonal coordinates:
 // https://www.redblobgames.com/grids/hexagons/.
 // We'll use cube coordinates here.
@@ -18,15 +21,75 @@ pub fn solve(input: &str) -> Option<Box<usize>> {
   Some(Box::new(answer))
 }
 
+pub const PARAMS2: &[Param] = &[Param {
+  name: "days",
+  default: "100",
+  doc: "Number of days the exhibit runs.",
+  check: parses::<u32>,
+}];
+
 pub fn solve2(input: &str) -> Option<Box<usize>> {
+  solve2_with(input, &Params::defaults(PARAMS2))
+}
+
+pub fn solve2_with(input: &str, params: &Params) -> Option<Box<usize>> {
   let tiles = get_arrangement(input);
 
-  let tiles = advance(tiles, 100);
+  let tiles = advance(tiles, params.get("days"));
 
   let answer = count_black_tiles(&tiles);
   Some(Box::new(answer))
 }
 
+/// The initial arrangement as a single generation.
+pub fn simulation(input: &str) -> Simulation {
+  let tiles = get_arrangement(input);
+
+  Simulation {
+    states: &STATES,
+    generations: vec![to_generation(&tiles)],
+  }
+}
+
+/// The initial arrangement and each of the following `days`.
+pub fn simulation2(input: &str, params: &Params) -> Simulation {
+  let tiles = get_arrangement(input);
+  let mut generations = vec![to_generation(&tiles)];
+
+  advance_with(tiles, params.get("days"), |tiles| generations.push(to_generation(tiles)));
+
+  Simulation {
+    states: &STATES,
+    generations,
+  }
+}
+
+const STATES: [State; 2] = [
+  State {
+    name: "white",
+    color: [0xF0, 0xF0, 0xF0],
+  },
+  State {
+    name: "black",
+    color: [0x00, 0x00, 0x00],
+  },
+];
+
+/// Lays out black tiles on a grid using doubled coordinates:
+/// each hex spans two cells in a row, and odd rows are shifted by one cell.
+fn to_generation(tiles: &Tiles) -> Vec<((i64, i64), usize)> {
+  tiles
+    .iter()
+    .filter(|(_coords, color)| **color)
+    .flat_map(|(&(x, _y, z), _color)| {
+      let row = z as i64;
+      let col = (2 * x + z) as i64;
+
+      vec![((row, col), 1), ((row, col + 1), 1)]
+    })
+    .collect()
+}
+
 fn get_arrangement(input: &str) -> Tiles {
   let paths = input
     .trim_end()
@@ -136,6 +199,14 @@ fn add(coords1: Coords, coords2: Coords) -> Coords {
 }
 
 fn advance(tiles: Tiles, moves: u32) -> Tiles {
+  advance_with(tiles, moves, |_tiles| ())
+}
+
+/// Runs `moves` days of the exhibit, calling `on_move` with the tiles after each day.
+fn advance_with<F>(tiles: Tiles, moves: u32, mut on_move: F) -> Tiles
+where
+  F: FnMut(&Tiles),
+{
   let mut prev = tiles;
   complement(&mut prev);
 
@@ -175,6 +246,7 @@ fn advance(tiles: Tiles, moves: u32) -> Tiles {
       }
     }
 
+    on_move(&next);
     prev = next;
   }
 
@@ -0,0 +1,593 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::params::{Param, Params};
+use crate::{d11, d17, d24};
+
+pub type Rgb = [u8; 3];
+
+/// A named state of a simulation cell together with its default color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct State {
+  pub name: &'static str,
+  pub color: Rgb,
+}
+
+/// All generations of a cellular automaton.
+///
+/// Each generation lists `(row, col)` coordinates of cells together with
+/// an index into `states`. Cells that are not listed are drawn
+/// with the first state, which serves as a background.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Simulation {
+  pub states: &'static [State],
+  pub generations: Vec<Vec<((i64, i64), usize)>>,
+}
+
+const USAGE: &str =
+  "Usage: export <command> [input] [name=value ...] --frames out.gif [--palette state=#rrggbb,...] [--scale N] [--delay N].";
+
+type Simulate = fn(&str, &Params) -> Simulation;
+
+/// Exportable commands with their simulations and the parameters those accept.
+const SIMULATIONS: [(&str, Simulate, &[Param]); 6] = [
+  ("d11", |input, _params| d11::simulation(input), &[]),
+  ("d11_2", |input, _params| d11::simulation2(input), &[]),
+  ("d17", d17::simulation, d17::PARAMS),
+  ("d17_2", d17::simulation2, d17::PARAMS),
+  ("d24", |input, _params| d24::simulation(input), &[]),
+  ("d24_2", d24::simulation2, d24::PARAMS2),
+];
+
+/// A single rendered image, `pixels` are indices into a palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Frame {
+  width: usize,
+  height: usize,
+  pixels: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Options {
+  command: String,
+  input_file: PathBuf,
+  args: Vec<(String, String)>,
+  output: PathBuf,
+  palette: HashMap<String, Rgb>,
+  scale: usize,
+  delay: u16,
+}
+
+/// Runs the `export` REPL command, see `USAGE`.
+///
+/// If `out` ends with `.png`, a sequence of `out_000.png`, `out_001.png`, ... files is written instead of a GIF.
+pub fn run(line: &str) -> Result<String, String> {
+  let options = parse_options(line)?;
+
+  let (simulate, declared) = SIMULATIONS
+    .iter()
+    .find(|(name, _, _)| *name == options.command)
+    .map(|(_, simulate, declared)| (simulate, declared))
+    .ok_or_else(|| {
+      let names = SIMULATIONS.iter().map(|(name, _, _)| *name).collect::<Vec<_>>();
+      format!(
+        "Cannot export {:?}, supported commands: {}.",
+        options.command,
+        names.join(", ")
+      )
+    })?;
+  let params = Params::resolve(declared, &options.args)?;
+
+  let input = fs::read_to_string(&options.input_file)
+    .map_err(|error| format!("Cannot read input file {:?} due to {:?}.", &options.input_file, error))?;
+
+  let simulation = simulate(&input, &params);
+  let palette = palette(simulation.states, &options.palette)?;
+  let frames = render(&simulation, options.scale);
+
+  let is_png = options.output.extension().is_some_and(|extension| extension == "png");
+  let written = if is_png {
+    write_png_sequence(&options.output, &frames, &palette)?
+  } else {
+    fs::write(&options.output, encode_gif(&frames, &palette, options.delay))
+      .map_err(|error| format!("Cannot write {:?} due to {:?}.", &options.output, error))?;
+    vec![options.output.clone()]
+  };
+
+  Ok(format!(
+    "Exported {} frames of {} to {}.",
+    frames.len(),
+    options.command,
+    written
+      .iter()
+      .map(|path| path.display().to_string())
+      .collect::<Vec<_>>()
+      .join(", ")
+  ))
+}
+
+fn parse_options(line: &str) -> Result<Options, String> {
+  let mut positional = vec![];
+  let mut args = vec![];
+  let mut output = None;
+  let mut palette = HashMap::new();
+  let mut scale = 4;
+  let mut delay = 10;
+
+  let mut items = line.split_ascii_whitespace().skip(1);
+  while let Some(item) = items.next() {
+    match item {
+      "--frames" | "--palette" | "--scale" | "--delay" => {
+        let value = items.next().ok_or_else(|| format!("Missing value for {}.", item))?;
+
+        match item {
+          "--frames" => output = Some(PathBuf::from(value)),
+          "--palette" => {
+            for assignment in value.split(',') {
+              match assignment.split('=').collect::<Vec<_>>()[..] {
+                [state, color] => {
+                  palette.insert(state.to_string(), parse_color(color)?);
+                }
+                _ => return Err(format!("Expected state=#rrggbb in the palette, got {:?}.", assignment)),
+              }
+            }
+          }
+          "--scale" => scale = value.parse().map_err(|_| format!("Invalid scale {:?}.", value))?,
+          _ => delay = value.parse().map_err(|_| format!("Invalid delay {:?}.", value))?,
+        }
+      }
+      _ => match item.split_once('=') {
+        Some((name, value)) => args.push((name.to_string(), value.to_string())),
+        None => positional.push(item),
+      },
+    }
+  }
+
+  let (command, input_file) = match positional[..] {
+    [command] => (command, crate::task_name_to_default_input_path(command)),
+    [command, input_file] => (command, Some(Path::new("inputs").join(input_file))),
+    _ => ("", None),
+  };
+
+  match (input_file, output) {
+    (Some(input_file), Some(output)) if scale > 0 => Ok(Options {
+      command: command.to_string(),
+      input_file,
+      args,
+      output,
+      palette,
+      scale,
+      delay,
+    }),
+    _ => Err(USAGE.to_string()),
+  }
+}
+
+fn parse_color(color: &str) -> Result<Rgb, String> {
+  let hex = color.trim_start_matches('#');
+  let channel = |idx: usize| {
+    hex
+      .get(idx..idx + 2)
+      .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+  };
+
+  match (hex.len(), channel(0), channel(2), channel(4)) {
+    (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+    _ => Err(format!("Invalid color {:?}, expected #rrggbb.", color)),
+  }
+}
+
+/// Builds a palette from the default state colors, applying `overrides` by state name.
+fn palette(states: &[State], overrides: &HashMap<String, Rgb>) -> Result<Vec<Rgb>, String> {
+  for name in overrides.keys() {
+    if !states.iter().any(|state| state.name == name) {
+      let names = states.iter().map(|state| state.name).collect::<Vec<_>>();
+      return Err(format!(
+        "Unknown state {:?}, expected one of: {}.",
+        name,
+        names.join(", ")
+      ));
+    }
+  }
+
+  Ok(
+    states
+      .iter()
+      .map(|state| *overrides.get(state.name).unwrap_or(&state.color))
+      .collect(),
+  )
+}
+
+/// Renders all generations on a shared canvas covering every cell that ever appeared,
+/// drawing each cell as a `scale`x`scale` square.
+fn render(simulation: &Simulation, scale: usize) -> Vec<Frame> {
+  let cells = simulation.generations.iter().flatten().map(|(coords, _state)| coords);
+  let min_row = cells.clone().map(|(row, _)| *row).min().unwrap_or(0);
+  let max_row = cells.clone().map(|(row, _)| *row).max().unwrap_or(0);
+  let min_col = cells.clone().map(|(_, col)| *col).min().unwrap_or(0);
+  let max_col = cells.map(|(_, col)| *col).max().unwrap_or(0);
+
+  let width = (max_col - min_col + 1) as usize * scale;
+  let height = (max_row - min_row + 1) as usize * scale;
+
+  simulation
+    .generations
+    .iter()
+    .map(|generation| {
+      let mut pixels = vec![0; width * height];
+
+      for ((row, col), state) in generation.iter() {
+        let y = (row - min_row) as usize * scale;
+        let x = (col - min_col) as usize * scale;
+
+        for dy in 0..scale {
+          let offset = (y + dy) * width + x;
+          for pixel in pixels[offset..offset + scale].iter_mut() {
+            *pixel = *state as u8;
+          }
+        }
+      }
+
+      Frame { width, height, pixels }
+    })
+    .collect()
+}
+
+fn write_png_sequence(output: &Path, frames: &[Frame], palette: &[Rgb]) -> Result<Vec<PathBuf>, String> {
+  let stem = output.file_stem().and_then(|stem| stem.to_str()).unwrap_or("frame");
+
+  frames
+    .iter()
+    .enumerate()
+    .map(|(idx, frame)| {
+      let path = output.with_file_name(format!("{}_{:03}.png", stem, idx));
+      fs::write(&path, encode_png(frame, palette))
+        .map(|_| path.clone())
+        .map_err(|error| format!("Cannot write {:?} due to {:?}.", &path, error))
+    })
+    .collect()
+}
+
+/// Encodes `frames` as a looping GIF89a animation, `delay` is in hundredths of a second.
+fn encode_gif(frames: &[Frame], palette: &[Rgb], delay: u16) -> Vec<u8> {
+  let width = frames.first().map_or(1, |frame| frame.width) as u16;
+  let height = frames.first().map_or(1, |frame| frame.height) as u16;
+
+  // the color table size has to be a power of two, at least 2 entries
+  let mut color_bits = 1;
+  while (1 << color_bits) < palette.len() {
+    color_bits += 1;
+  }
+
+  let mut out = b"GIF89a".to_vec();
+  out.extend_from_slice(&width.to_le_bytes());
+  out.extend_from_slice(&height.to_le_bytes());
+  // global color table present, 8 bits per channel, table size
+  out.push(0b1111_0000 | (color_bits - 1));
+  out.extend_from_slice(&[0, 0]);
+
+  for idx in 0..(1 << color_bits) {
+    out.extend_from_slice(palette.get(idx).unwrap_or(&[0, 0, 0]));
+  }
+
+  // NETSCAPE2.0 application extension makes the animation loop forever
+  out.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+  out.extend_from_slice(b"NETSCAPE2.0");
+  out.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+
+  let min_code_size = color_bits.max(2);
+  for frame in frames {
+    // graphic control extension with the frame delay
+    out.extend_from_slice(&[0x21, 0xF9, 0x04, 0x00]);
+    out.extend_from_slice(&delay.to_le_bytes());
+    out.extend_from_slice(&[0x00, 0x00]);
+
+    // image descriptor
+    out.push(0x2C);
+    out.extend_from_slice(&[0, 0, 0, 0]);
+    out.extend_from_slice(&(frame.width as u16).to_le_bytes());
+    out.extend_from_slice(&(frame.height as u16).to_le_bytes());
+    out.push(0);
+
+    out.push(min_code_size);
+    for block in lzw_encode(&frame.pixels, min_code_size).chunks(255) {
+      out.push(block.len() as u8);
+      out.extend_from_slice(block);
+    }
+    out.push(0);
+  }
+
+  out.push(0x3B);
+  out
+}
+
+/// Variable code size LZW compression, as used in GIF image data.
+fn lzw_encode(pixels: &[u8], min_code_size: u8) -> Vec<u8> {
+  const MAX_CODE: u16 = 4095;
+
+  let clear = 1u16 << min_code_size;
+  let end_of_information = clear + 1;
+
+  let mut writer = BitWriter::default();
+  let mut dictionary: HashMap<(u16, u8), u16> = HashMap::new();
+  let mut next_code = clear + 2;
+  let mut code_size = min_code_size + 1;
+
+  writer.write(clear, code_size);
+
+  let mut prefix: Option<u16> = None;
+  for &pixel in pixels {
+    prefix = match prefix {
+      None => Some(pixel as u16),
+      Some(code) => match dictionary.get(&(code, pixel)) {
+        Some(&extended) => Some(extended),
+        None => {
+          writer.write(code, code_size);
+
+          if next_code <= MAX_CODE {
+            dictionary.insert((code, pixel), next_code);
+            next_code += 1;
+
+            if next_code > (1 << code_size) && code_size < 12 {
+              code_size += 1;
+            }
+          } else {
+            writer.write(clear, code_size);
+            dictionary.clear();
+            next_code = clear + 2;
+            code_size = min_code_size + 1;
+          }
+
+          Some(pixel as u16)
+        }
+      },
+    };
+  }
+
+  if let Some(code) = prefix {
+    writer.write(code, code_size);
+  }
+  writer.write(end_of_information, code_size);
+
+  writer.finish()
+}
+
+/// Packs codes least significant bit first.
+#[derive(Debug, Default)]
+struct BitWriter {
+  bytes: Vec<u8>,
+  buffer: u32,
+  bits: u8,
+}
+
+impl BitWriter {
+  fn write(&mut self, code: u16, size: u8) {
+    self.buffer |= (code as u32) << self.bits;
+    self.bits += size;
+
+    while self.bits >= 8 {
+      self.bytes.push(self.buffer as u8);
+      self.buffer >>= 8;
+      self.bits -= 8;
+    }
+  }
+
+  fn finish(mut self) -> Vec<u8> {
+    if self.bits > 0 {
+      self.bytes.push(self.buffer as u8);
+    }
+
+    self.bytes
+  }
+}
+
+/// Encodes `frame` as an 8-bit RGB PNG. Image data is stored in uncompressed deflate blocks.
+fn encode_png(frame: &Frame, palette: &[Rgb]) -> Vec<u8> {
+  let mut raw = Vec::with_capacity((frame.width * 3 + 1) * frame.height);
+  for row in frame.pixels.chunks(frame.width.max(1)) {
+    // filter type: none
+    raw.push(0);
+    for pixel in row {
+      raw.extend_from_slice(&palette[*pixel as usize]);
+    }
+  }
+
+  // zlib header: deflate with 32K window, no preset dictionary, fastest compression
+  let mut zlib = vec![0x78, 0x01];
+  let blocks = raw.chunks(0xFFFF).collect::<Vec<_>>();
+  for (idx, block) in blocks.iter().enumerate() {
+    zlib.push((idx + 1 == blocks.len()) as u8);
+    zlib.extend_from_slice(&(block.len() as u16).to_le_bytes());
+    zlib.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+    zlib.extend_from_slice(block);
+  }
+  if blocks.is_empty() {
+    zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+  }
+  zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+  let mut header = vec![];
+  header.extend_from_slice(&(frame.width as u32).to_be_bytes());
+  header.extend_from_slice(&(frame.height as u32).to_be_bytes());
+  // bit depth 8, color type RGB, default compression, filtering and no interlace
+  header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+  let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+  png_chunk(&mut out, b"IHDR", &header);
+  png_chunk(&mut out, b"IDAT", &zlib);
+  png_chunk(&mut out, b"IEND", &[]);
+  out
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+  out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+  let mut checked = kind.to_vec();
+  checked.extend_from_slice(data);
+  out.extend_from_slice(&checked);
+  out.extend_from_slice(&crc32(&checked).to_be_bytes());
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+  let mut crc = 0xFFFF_FFFFu32;
+
+  for byte in bytes {
+    crc ^= *byte as u32;
+    for _ in 0..8 {
+      crc = if crc & 1 == 1 {
+        (crc >> 1) ^ 0xEDB8_8320
+      } else {
+        crc >> 1
+      };
+    }
+  }
+
+  !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+  let (mut a, mut b) = (1u32, 0u32);
+
+  for byte in bytes {
+    a = (a + *byte as u32) % 65521;
+    b = (b + a) % 65521;
+  }
+
+  (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Reference LZW decoder, used to check that the encoder output round-trips.
+  fn lzw_decode(data: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear = 1usize << min_code_size;
+    let mut table: Vec<Vec<u8>> = vec![];
+    let mut code_size = min_code_size + 1;
+    let mut previous: Option<Vec<u8>> = None;
+    let mut out = vec![];
+
+    let (mut buffer, mut bits, mut offset) = (0u32, 0u8, 0);
+    loop {
+      while bits < code_size {
+        buffer |= (data[offset] as u32) << bits;
+        offset += 1;
+        bits += 8;
+      }
+      let code = (buffer & ((1 << code_size) - 1)) as usize;
+      buffer >>= code_size;
+      bits -= code_size;
+
+      if code == clear {
+        table = (0..clear).map(|idx| vec![idx as u8]).collect();
+        table.push(vec![]);
+        table.push(vec![]);
+        code_size = min_code_size + 1;
+        previous = None;
+        continue;
+      } else if code == clear + 1 {
+        return out;
+      }
+
+      let entry = match (table.get(code), &previous) {
+        (Some(entry), _) if code != table.len() => entry.clone(),
+        (_, Some(previous)) => {
+          let mut entry = previous.clone();
+          entry.push(previous[0]);
+          entry
+        }
+        _ => panic!("invalid code {}", code),
+      };
+
+      if let Some(previous) = previous {
+        if table.len() < 4096 {
+          let mut new_entry = previous.clone();
+          new_entry.push(entry[0]);
+          table.push(new_entry);
+
+          if table.len() == (1 << code_size) && code_size < 12 {
+            code_size += 1;
+          }
+        }
+      }
+
+      out.extend_from_slice(&entry);
+      previous = Some(entry);
+    }
+  }
+
+  #[test]
+  fn lzw_round_trips() {
+    let pixels = vec![0, 1, 1, 1, 2, 2, 0, 0, 0, 1, 2, 3, 3, 3, 3, 0, 1, 0, 1, 0, 1];
+    assert_eq!(lzw_decode(&lzw_encode(&pixels, 2), 2), pixels);
+
+    // long enough to overflow the 12-bit dictionary
+    let mut state = 7u32;
+    let pixels = (0..100_000)
+      .map(|_| {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        ((state >> 16) % 3) as u8
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(lzw_decode(&lzw_encode(&pixels, 2), 2), pixels);
+  }
+
+  #[test]
+  fn checksums_work() {
+    assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+  }
+
+  #[test]
+  fn options_are_parsed() {
+    let options = parse_options("export d11 sample11 --frames out.gif --palette occupied=#ff0000 --scale 2").unwrap();
+    assert_eq!(options.command, "d11");
+    assert_eq!(options.input_file, Path::new("inputs").join("sample11"));
+    assert_eq!(options.output, PathBuf::from("out.gif"));
+    assert_eq!(options.palette.get("occupied"), Some(&[255, 0, 0]));
+    assert_eq!(options.scale, 2);
+    assert!(options.args.is_empty());
+
+    let options = parse_options("export d17 sample17 cycles=2 --frames out.gif").unwrap();
+    assert_eq!(options.input_file, Path::new("inputs").join("sample17"));
+    assert_eq!(options.args, vec![("cycles".to_string(), "2".to_string())]);
+
+    assert!(parse_options("export d11 sample11").is_err());
+    assert!(parse_options("export d11 --frames out.gif --palette occupied=red").is_err());
+  }
+
+  #[test]
+  fn simulations_follow_params() {
+    let input = fs::read_to_string("inputs/sample17").unwrap();
+    let params = Params::resolve(d17::PARAMS, &[("cycles".to_string(), "2".to_string())]).unwrap();
+    assert_eq!(d17::simulation(&input, &params).generations.len(), 3);
+
+    let (_, simulate, declared) = SIMULATIONS.iter().find(|(name, _, _)| *name == "d24_2").unwrap();
+    let input = fs::read_to_string("inputs/sample24").unwrap();
+    let params = Params::resolve(declared, &[("days".to_string(), "5".to_string())]).unwrap();
+    assert_eq!(simulate(&input, &params).generations.len(), 6);
+  }
+
+  #[test]
+  fn gif_is_rendered() {
+    let input = fs::read_to_string("inputs/sample11").unwrap();
+    let simulation = d11::simulation(&input);
+    // the initial layout plus generations till the layout stabilizes
+    assert_eq!(simulation.generations.len(), 6);
+
+    let palette = palette(simulation.states, &HashMap::new()).unwrap();
+    let frames = render(&simulation, 3);
+    assert_eq!((frames[0].width, frames[0].height), (30, 30));
+
+    let gif = encode_gif(&frames, &palette, 10);
+    assert_eq!(&gif[..6], b"GIF89a");
+    assert_eq!(&gif[6..10], &[30, 0, 30, 0]);
+    assert_eq!(gif.last(), Some(&0x3B));
+
+    let png = encode_png(&frames[0], &palette);
+    assert_eq!(&png[1..4], b"PNG");
+    assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+  }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+
+/// A minimal JSON value, enough to serialize reports and API responses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+  Null,
+  Bool(bool),
+  Number(f64),
+  String(String),
+  Array(Vec<Json>),
+  Object(Vec<(String, Json)>),
+}
+
+impl Json {
+  /// Builds an object, preserving the order of `fields`.
+  pub fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(
+      fields
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect(),
+    )
+  }
+}
+
+impl From<&str> for Json {
+  fn from(value: &str) -> Json {
+    Json::String(value.to_string())
+  }
+}
+
+impl From<String> for Json {
+  fn from(value: String) -> Json {
+    Json::String(value)
+  }
+}
+
+impl From<bool> for Json {
+  fn from(value: bool) -> Json {
+    Json::Bool(value)
+  }
+}
+
+impl From<usize> for Json {
+  fn from(value: usize) -> Json {
+    Json::Number(value as f64)
+  }
+}
+
+impl From<i64> for Json {
+  fn from(value: i64) -> Json {
+    Json::Number(value as f64)
+  }
+}
+
+impl From<f64> for Json {
+  fn from(value: f64) -> Json {
+    Json::Number(value)
+  }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+  fn from(value: Option<T>) -> Json {
+    value.map_or(Json::Null, Into::into)
+  }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+  fn from(values: Vec<T>) -> Json {
+    Json::Array(values.into_iter().map(Into::into).collect())
+  }
+}
+
+impl fmt::Display for Json {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Json::Null => write!(f, "null"),
+      Json::Bool(value) => write!(f, "{}", value),
+      Json::Number(value) if !value.is_finite() => write!(f, "null"),
+      Json::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => write!(f, "{}", *value as i64),
+      Json::Number(value) => write!(f, "{}", value),
+      Json::String(value) => write_string(f, value),
+      Json::Array(values) => {
+        write!(f, "[")?;
+        for (idx, value) in values.iter().enumerate() {
+          if idx > 0 {
+            write!(f, ",")?;
+          }
+          write!(f, "{}", value)?;
+        }
+        write!(f, "]")
+      }
+      Json::Object(fields) => {
+        write!(f, "{{")?;
+        for (idx, (key, value)) in fields.iter().enumerate() {
+          if idx > 0 {
+            write!(f, ",")?;
+          }
+          write_string(f, key)?;
+          write!(f, ":{}", value)?;
+        }
+        write!(f, "}}")
+      }
+    }
+  }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+  write!(f, "\"")?;
+
+  for ch in value.chars() {
+    match ch {
+      '"' => write!(f, "\\\"")?,
+      '\\' => write!(f, "\\\\")?,
+      '\n' => write!(f, "\\n")?,
+      '\r' => write!(f, "\\r")?,
+      '\t' => write!(f, "\\t")?,
+      ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+      ch => write!(f, "{}", ch)?,
+    }
+  }
+
+  write!(f, "\"")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn serialization_works() {
+    let json = Json::object(vec![
+      ("answer", Json::from("\"42\"\n")),
+      ("elapsed_ms", Json::from(1.5)),
+      ("count", Json::from(3usize)),
+      ("error", Json::from(None::<String>)),
+      ("tags", Json::from(vec!["a", "b"])),
+    ]);
+
+    assert_eq!(
+      json.to_string(),
+      r#"{"answer":"\"42\"\n","elapsed_ms":1.5,"count":3,"error":null,"tags":["a","b"]}"#
+    );
+  }
+}
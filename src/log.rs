@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::RwLock;
+
+/// Logging verbosity, messages are shown if their level is at most the configured one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+  Off,
+  Error,
+  Warn,
+  Info,
+  Debug,
+  Trace,
+}
+
+impl Level {
+  fn parse(level: &str) -> Result<Level, String> {
+    match level.to_ascii_lowercase().as_str() {
+      "off" => Ok(Level::Off),
+      "error" => Ok(Level::Error),
+      "warn" => Ok(Level::Warn),
+      "info" => Ok(Level::Info),
+      "debug" => Ok(Level::Debug),
+      "trace" => Ok(Level::Trace),
+      _ => Err(format!(
+        "Unknown log level {:?}, expected one of: off, error, warn, info, debug, trace.",
+        level
+      )),
+    }
+  }
+}
+
+impl fmt::Display for Level {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let name = match self {
+      Level::Off => "off",
+      Level::Error => "error",
+      Level::Warn => "warn",
+      Level::Info => "info",
+      Level::Debug => "debug",
+      Level::Trace => "trace",
+    };
+
+    write!(f, "{}", name)
+  }
+}
+
+/// The default level, and overrides for targets (day modules, like `d20`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Config {
+  default: Level,
+  targets: HashMap<String, Level>,
+}
+
+lazy_static! {
+  static ref CONFIG: RwLock<Config> = RwLock::new(Config {
+    default: Level::Info,
+    targets: HashMap::new(),
+  });
+}
+
+/// Converts `module_path!()`, like `aoc2020::d20`, to a target name, like `d20`.
+pub fn target(module_path: &str) -> &str {
+  module_path.rsplit("::").next().unwrap_or(module_path)
+}
+
+pub fn enabled(target: &str, level: Level) -> bool {
+  let config = CONFIG.read().unwrap();
+  level != Level::Off && level <= *config.targets.get(target).unwrap_or(&config.default)
+}
+
+/// Writes a message to stderr, use the `error!`, ..., `trace!` macros instead of calling this directly.
+pub fn write(target: &str, level: Level, message: fmt::Arguments) {
+  for line in message.to_string().lines() {
+    eprintln!("[{} {}] {}", target, level, line);
+  }
+}
+
+/// Applies a `:log` spec: space or comma separated `level` entries setting the default level,
+/// and `target=level` entries setting the level of a single target (`target=default` resets it).
+pub fn configure(spec: &str) -> Result<(), String> {
+  let mut config = CONFIG.read().unwrap().clone();
+
+  for entry in spec
+    .split(|ch: char| ch == ',' || ch.is_whitespace())
+    .filter(|entry| !entry.is_empty())
+  {
+    match entry.split_once('=') {
+      Some((target, "default")) => {
+        config.targets.remove(target);
+      }
+      Some((target, level)) => {
+        config.targets.insert(target.to_string(), Level::parse(level)?);
+      }
+      None => config.default = Level::parse(entry)?,
+    }
+  }
+
+  *CONFIG.write().unwrap() = config;
+  Ok(())
+}
+
+/// Describes the current configuration for the `:log` command.
+pub fn describe() -> String {
+  let config = CONFIG.read().unwrap();
+
+  let mut targets = config
+    .targets
+    .iter()
+    .map(|(target, level)| format!("{}={}", target, level))
+    .collect::<Vec<_>>();
+  targets.sort();
+
+  if targets.is_empty() {
+    format!("Log level: {}.", config.default)
+  } else {
+    format!("Log level: {}, targets: {}.", config.default, targets.join(", "))
+  }
+}
+
+#[macro_export]
+macro_rules! log {
+  ($level:expr, $($arg:tt)+) => {{
+    let target = $crate::log::target(module_path!());
+    if $crate::log::enabled(target, $level) {
+      $crate::log::write(target, $level, format_args!($($arg)+));
+    }
+  }};
+}
+
+#[macro_export]
+macro_rules! error {
+  ($($arg:tt)+) => ($crate::log!($crate::log::Level::Error, $($arg)+))
+}
+
+#[macro_export]
+macro_rules! warn {
+  ($($arg:tt)+) => ($crate::log!($crate::log::Level::Warn, $($arg)+))
+}
+
+#[macro_export]
+macro_rules! info {
+  ($($arg:tt)+) => ($crate::log!($crate::log::Level::Info, $($arg)+))
+}
+
+#[macro_export]
+macro_rules! debug {
+  ($($arg:tt)+) => ($crate::log!($crate::log::Level::Debug, $($arg)+))
+}
+
+#[macro_export]
+macro_rules! trace {
+  ($($arg:tt)+) => ($crate::log!($crate::log::Level::Trace, $($arg)+))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn targets_are_configured() {
+    assert_eq!(target("aoc2020::d20"), "d20");
+    assert_eq!(target("aoc2020"), "aoc2020");
+
+    configure("test_target=debug").unwrap();
+    assert!(enabled("test_target", Level::Debug));
+    assert!(!enabled("test_target", Level::Trace));
+    assert!(describe().contains("test_target=debug"));
+
+    configure("test_target=off").unwrap();
+    assert!(!enabled("test_target", Level::Error));
+
+    configure("test_target=default").unwrap();
+    assert!(!describe().contains("test_target"));
+
+    assert!(configure("test_target=loud").is_err());
+    assert!(configure("verbose").is_err());
+  }
+}
@@ -1,15 +1,64 @@
 #[macro_use]
 extern crate lazy_static;
 
-use std::time::Instant;
+use std::panic;
+use std::process;
+use std::time::{Duration, Instant};
 use std::collections::HashMap;
+use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 
-type Solver = fn(&str) -> Option<Box<dyn std::fmt::Debug>>;
+use params::{Param, Params};
+
+#[macro_use]
+mod log;
+
+mod export;
+mod json;
+mod params;
+mod report;
+mod script;
+mod server;
+
+type Solver = fn(&str, &Params) -> Option<Box<dyn std::fmt::Debug>>;
+
+/// A REPL command: a solver together with the parameters it accepts.
+#[derive(Clone, Copy)]
+struct Command {
+  solver: Solver,
+  params: &'static [Param],
+}
+
+type Commands = HashMap<&'static str, Command>;
+
+/// This macro wraps a solver function into a `Command`.
+///
+/// Solvers without declared parameters take only the input,
+/// solvers with parameters also take `&Params` resolved from `params`.
+macro_rules! command {
+  ($solver:path) => {
+    Command {
+      // Note the cast to  `Box<dyn std::fmt::Debug>` here and below.
+      //
+      // This is needed to allow `solve` functions in the day modules
+      // to return any `Option<Box<T>>`, as long as this `T` implements `Debug`;
+      // This prevents errors when calling `assert_eq!` on results
+      // of those functions.
+      solver: |input: &str, _params: &Params| $solver(input).map(|x| x as Box<dyn std::fmt::Debug>),
+      params: &[],
+    }
+  };
+  ($solver:path, $params:expr) => {
+    Command {
+      solver: |input: &str, params: &Params| $solver(input, params).map(|x| x as Box<dyn std::fmt::Debug>),
+      params: $params,
+    }
+  };
+}
 
 /// This macro counts a number of repetitions of some token.
 macro_rules! count {
@@ -20,32 +69,15 @@ macro_rules! count {
 /// This macro defines passed modules, and corresponding
 /// `module_name` and `module_name_2` commands for the REPL.
 ///
-/// You can also add custom commands in the `commands` map
-/// in the `main()` function.
+/// You can also add custom commands, or commands with parameters,
+/// in the `commands()` function.
 macro_rules! commands {
   ($($module:ident),*) => {
      $(mod $module;)*
 
-    const COMMANDS: [(&'static str, Solver); count!($($module,)*)] = [
-      $(
-        (
-          stringify!($module),
-          // Note the cast to  `Box<dyn std::fmt::Debug>` here and below.
-          //
-          // This is needed to allow `solve` functions in the day modules
-          // to return any `Option<Box<T>>`, as long as this `T` implements `Debug`;
-          // This prevents errors when calling `assert_eq!` on results
-          // of those functions.
-          |input: &str| $module::solve(input).map(|x| (x as Box<dyn std::fmt::Debug>))
-        ),
-      )*
-
-      $(
-        (
-          concat!(stringify!($module), "_2"),
-          |input: &str| $module::solve2(input).map(|x| (x as Box<dyn std::fmt::Debug>))
-        ),
-      )*
+    const COMMANDS: [(&'static str, Command); count!($($module,)*)] = [
+      $((stringify!($module), command!($module::solve)),)*
+      $((concat!(stringify!($module), "_2"), command!($module::solve2)),)*
     ];
   };
 }
@@ -56,19 +88,20 @@ commands!(
 );
 
 fn main() {
-  let mut rl = Editor::<()>::new();
+  let commands = commands();
+
+  // non-interactive mode: execute arguments as a single REPL line
+  let args = env::args().skip(1).collect::<Vec<_>>();
+  if !args.is_empty() {
+    if let Err(error) = execute_line(&args.join(" "), &commands) {
+      eprintln!("{}", error);
+      process::exit(1);
+    }
 
-  let mut commands: HashMap<&'static str, Solver> = COMMANDS.iter().cloned().collect();
-  // special commands should go here
-  commands.insert("d11_debug", |input: &str| {
-    d11::solve_debug(input).map(|x| (x as Box<dyn std::fmt::Debug>))
-  });
-  commands.insert("d11_2_debug", |input: &str| {
-    d11::solve2_debug(input).map(|x| (x as Box<dyn std::fmt::Debug>))
-  });
-  commands.insert("d17_3", |input: &str| {
-    d17::go_bananas(input).map(|x| (x as Box<dyn std::fmt::Debug>))
-  });
+    return;
+  }
+
+  let mut rl = Editor::<()>::new();
 
   loop {
     let readline = rl.readline(">>> ");
@@ -77,31 +110,8 @@ fn main() {
       Ok(line) => {
         rl.add_history_entry(line.as_str());
 
-        if &line == "all" {
-          for (name, solver) in commands.iter() {
-            if !name.contains("debug") && name != &"d16_2" && name != &"d17_3" {
-              if let Some(input_file) = solver_name_to_default_input_path(&commands, name) {
-                println!("Running {}", name);
-                run_command(solver, input_file);
-                println!();
-              }
-            }
-          }
-        } else if &line == "list" {
-          let mut command_names = commands.keys().map(|x| *x).collect::<Vec<_>>();
-          command_names.sort();
-
-          println!(
-            "The following commands are defined:\n{}",
-            textwrap::fill(&command_names.join(", "), 80)
-          );
-        } else if &line == "next" {
-          gen_next_day();
-        } else if let Some((solver, input_file)) = parse_line(&line) {
-          match commands.get(solver) {
-            None => println!("Unrecoginzed command: {:?}.", &line),
-            Some(solver) => run_command(solver, input_file),
-          }
+        if let Err(error) = execute_line(&line, &commands) {
+          println!("{}", error);
         }
       }
 
@@ -118,17 +128,169 @@ fn main() {
   }
 }
 
-fn parse_line(line: &str) -> Option<(&str, PathBuf)> {
-  let items = line.split_ascii_whitespace().collect::<Vec<_>>();
-  match &items[..] {
+/// Executes a single REPL line, returning an error if the command has failed.
+fn execute_line(line: &str, commands: &Commands) -> Result<(), String> {
+  let line = line.trim();
+
+  if line == "all" {
+    for (name, command) in commands.iter() {
+      if !name.contains("debug") && name != &"d16_2" && name != &"d17_3" {
+        if let Some(input_file) = solver_name_to_default_input_path(commands, name) {
+          println!("Running {}", name);
+          if let Err(error) = run_command(command, input_file, &Params::defaults(command.params)) {
+            println!("{}", error);
+          }
+          println!();
+        }
+      }
+    }
+  } else if line == "list" {
+    let mut command_names = commands.keys().map(|x| *x).collect::<Vec<_>>();
+    command_names.sort();
+
+    println!(
+      "The following commands are defined:\n{}",
+      textwrap::fill(&command_names.join(", "), 80)
+    );
+
+    println!("\nParameters, override with name=value:");
+    for name in command_names {
+      for param in commands[name].params {
+        println!("  {:<8} {}={} - {}", name, param.name, param.default, param.doc);
+      }
+    }
+  } else if line == "next" {
+    gen_next_day();
+  } else if line == ":log" || line.starts_with(":log ") {
+    log::configure(&line[":log".len()..])?;
+    println!("{}", log::describe());
+  } else if line.starts_with("export ") {
+    println!("{}", export::run(line)?);
+  } else if line == "serve" || line.starts_with("serve ") {
+    match parse_port(line) {
+      Some(port) => server::serve(port, commands).map_err(|error| format!("Server failed with {:?}", error))?,
+      None => return Err("Usage: serve [--port N].".to_string()),
+    }
+  } else if let Some(target) = line.strip_prefix("debug ") {
+    match parse_line(target) {
+      Some(invocation) if invocation.name == "d08" => d08::debug(&read_input(&invocation.input_file)?)?,
+      _ => return Err("Usage: debug d08 [input].".to_string()),
+    }
+  } else if let Some(path) = line.strip_prefix("source ") {
+    script::source(path.trim(), commands)?;
+  } else if line.starts_with("expect ") {
+    println!("{}", script::expect(line, commands)?);
+  } else if !line.is_empty() {
+    let invocation = parse_line(line).ok_or_else(|| format!("Unrecognized line {:?}", line))?;
+    match commands.get(invocation.name.as_str()) {
+      None => return Err(format!("Unrecoginzed command: {:?}.", line)),
+      Some(command) => {
+        let params = Params::resolve(command.params, &invocation.args)?;
+        run_command(command, invocation.input_file, &params)?
+      }
+    }
+  }
+
+  Ok(())
+}
+
+fn commands() -> Commands {
+  let mut commands: Commands = COMMANDS.iter().cloned().collect();
+
+  // special commands should go here
+  commands.insert("d01_all", command!(d01::combinations, d01::PARAMS));
+  commands.insert("d02_policies", command!(d02::solve_policies, d02::PARAMS));
+  commands.insert("d02_report", command!(d02::report, d02::REPORT_PARAMS));
+  commands.insert("d03_optimize", command!(d03::optimize, d03::OPTIMIZE_PARAMS));
+  commands.insert("d03_render", command!(d03::render, d03::RENDER_PARAMS));
+  commands.insert("d04_report", command!(d04::report, d04::REPORT_PARAMS));
+  commands.insert("d04_export", command!(d04::export, d04::EXPORT_PARAMS));
+  commands.insert("d05_map", command!(d05::seat_map, d05::PARAMS));
+  commands.insert("d06_histogram", command!(d06::histogram, d06::PARAMS));
+  commands.insert("d07_dot", command!(d07::dot, d07::DOT_PARAMS));
+  commands.insert("d07_query", command!(d07::query, d07::QUERY_PARAMS));
+  commands.insert("d07_validate", command!(d07::validate, d07::VALIDATE_PARAMS));
+  commands.insert("d08_cfg", command!(d08::cfg, d08::CFG_PARAMS));
+  commands.insert("d08_disasm", command!(d08::disassemble));
+  commands.insert("d08_repair", command!(d08::repair));
+  commands.insert("d08_run", command!(d08::run, d08::RUN_PARAMS));
+  commands.insert("d11_debug", command!(d11::solve_debug));
+  commands.insert("d11_2_debug", command!(d11::solve2_debug));
+  commands.insert("d17_3", command!(d17::go_bananas));
+
+  // commands with parameters replace the default ones
+  commands.insert("d01", command!(d01::solve_with, d01::PARAMS));
+  commands.insert("d01_2", command!(d01::solve2_with, d01::PARAMS2));
+  commands.insert("d03", command!(d03::solve_with, d03::PARAMS));
+  commands.insert("d03_2", command!(d03::solve2_with, d03::PARAMS2));
+  commands.insert("d04", command!(d04::solve_with, d04::PARAMS));
+  commands.insert("d04_2", command!(d04::solve2_with, d04::PARAMS2));
+  commands.insert("d05", command!(d05::solve_with, d05::PARAMS));
+  commands.insert("d05_2", command!(d05::solve2_with, d05::PARAMS));
+  commands.insert("d06", command!(d06::solve_with, d06::PARAMS));
+  commands.insert("d06_2", command!(d06::solve2_with, d06::PARAMS2));
+  commands.insert("d07", command!(d07::solve_with, d07::PARAMS));
+  commands.insert("d07_2", command!(d07::solve2_with, d07::PARAMS));
+  commands.insert("d09", command!(d09::solve_with, d09::PARAMS));
+  commands.insert("d09_2", command!(d09::solve2_with, d09::PARAMS));
+  commands.insert("d15", command!(d15::solve_with, d15::PARAMS));
+  commands.insert("d15_2", command!(d15::solve2_with, d15::PARAMS2));
+  commands.insert("d17", command!(d17::solve_with, d17::PARAMS));
+  commands.insert("d17_2", command!(d17::solve2_with, d17::PARAMS));
+  commands.insert("d23", command!(d23::solve_with, d23::PARAMS));
+  commands.insert("d23_2", command!(d23::solve2_with, d23::PARAMS2));
+  commands.insert("d24_2", command!(d24::solve2_with, d24::PARAMS2));
+
+  commands
+}
+
+/// A parsed `command [input] [name=value ...]` REPL line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Invocation {
+  name: String,
+  input_file: PathBuf,
+  args: Vec<(String, String)>,
+}
+
+fn parse_line(line: &str) -> Option<Invocation> {
+  let (args, items): (Vec<_>, Vec<_>) = params::split_args(line)
+    .into_iter()
+    .partition(|item| item.contains('='));
+
+  let args = args
+    .into_iter()
+    .map(|arg| {
+      let (name, value) = arg.split_once('=').unwrap();
+      (name.to_string(), value.to_string())
+    })
+    .collect();
+
+  let (name, input_file) = match &items[..] {
     // Default case for each day is to use its input file
-    &[day] => task_name_to_default_input_path(day).map(|input_file| (day, input_file)),
-    &[day, input_file] => Some((day, Path::new("inputs").join(input_file))),
+    [day] => (day, task_name_to_default_input_path(day)?),
+    // Paths are relative to `inputs`, unless they exist as is
+    [day, input_file] if Path::new(input_file).is_file() => (day, PathBuf::from(input_file)),
+    [day, input_file] => (day, Path::new("inputs").join(input_file)),
+    _ => return None,
+  };
+
+  Some(Invocation {
+    name: name.to_string(),
+    input_file,
+    args,
+  })
+}
+
+/// Parses `serve [--port N]`, the port defaults to 2020.
+fn parse_port(line: &str) -> Option<u16> {
+  match line.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+    ["serve"] => Some(2020),
+    ["serve", "--port", port] => port.parse().ok(),
     _ => None,
   }
 }
 
-fn solver_name_to_default_input_path(commands: &HashMap<&'static str, Solver>, solver_name: &str) -> Option<PathBuf> {
+fn solver_name_to_default_input_path(commands: &Commands, solver_name: &str) -> Option<PathBuf> {
   commands
     .iter()
     .find(|(name, _solver)| **name == solver_name)
@@ -144,20 +306,53 @@ fn task_name_to_default_input_path(task_name: &str) -> Option<PathBuf> {
     .map(|path| Path::new("inputs").join(path))
 }
 
-fn run_command<P>(solver: &Solver, input_file: P)
+fn run_command<P>(command: &Command, input_file: P, params: &Params) -> Result<(), String>
 where
   P: AsRef<Path> + std::fmt::Debug,
 {
-  match fs::read_to_string(&input_file) {
-    Ok(input) => {
-      let now = Instant::now();
+  let input = read_input(&input_file)?;
+  let execution = execute(command, &input, params);
 
-      let result = solver(&input);
+  match execution.result {
+    Ok(result) => {
       println!("{:?}", result);
+      println!("Elapsed: {:?}.", execution.elapsed);
+      Ok(())
+    }
+    Err(message) => Err(format!("Solver panicked: {}", message)),
+  }
+}
+
+fn read_input<P>(input_file: P) -> Result<String, String>
+where
+  P: AsRef<Path> + std::fmt::Debug,
+{
+  fs::read_to_string(&input_file)
+    .map_err(|error| format!("Cannot read input file {:?} due to {:?}.", &input_file, error))
+}
+
+/// Result of a solver run: the answer, or the panic message if the solver has panicked.
+struct Execution {
+  result: Result<Option<Box<dyn std::fmt::Debug>>, String>,
+  elapsed: Duration,
+}
+
+fn execute(command: &Command, input: &str, params: &Params) -> Execution {
+  let now = Instant::now();
 
-      println!("Elapsed: {:?}.", now.elapsed());
+  let result = panic::catch_unwind(|| (command.solver)(input, params)).map_err(|payload| {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+      message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+      message.clone()
+    } else {
+      "unknown panic".to_string()
     }
-    Err(error) => println!("Cannot read input file {:?} due to {:?}.", &input_file, error),
+  });
+
+  Execution {
+    result,
+    elapsed: now.elapsed(),
   }
 }
 
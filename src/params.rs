@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+/// A solver parameter, that can be overridden from the REPL with `name=value`.
+#[derive(Debug, Clone, Copy)]
+pub struct Param {
+  pub name: &'static str,
+  pub default: &'static str,
+  pub doc: &'static str,
+  /// Rejects invalid values before the solver runs, like `any` or `parses::<usize>`.
+  pub check: fn(&str) -> Result<(), String>,
+}
+
+/// Accepts any value, for parameters that are used as text.
+pub fn any(_value: &str) -> Result<(), String> {
+  Ok(())
+}
+
+/// Accepts values that parse as `T`.
+pub fn parses<T>(value: &str) -> Result<(), String>
+where
+  T: FromStr,
+  T::Err: Display,
+{
+  value.parse::<T>().map(|_| ()).map_err(|error| error.to_string())
+}
+
+/// Values of all declared parameters for a single solver run.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Params {
+  values: HashMap<String, String>,
+}
+
+impl Params {
+  pub fn defaults(declared: &[Param]) -> Params {
+    Params {
+      values: declared
+        .iter()
+        .map(|param| (param.name.to_string(), param.default.to_string()))
+        .collect(),
+    }
+  }
+
+  /// Applies `overrides` on top of the defaults, rejecting undeclared parameters and invalid values.
+  pub fn resolve(declared: &[Param], overrides: &[(String, String)]) -> Result<Params, String> {
+    let mut params = Params::defaults(declared);
+
+    for (name, value) in overrides {
+      let param = match declared.iter().find(|param| param.name == name) {
+        Some(param) => param,
+        None if declared.is_empty() => {
+          return Err(format!("Unknown parameter {:?}, this command has no parameters.", name));
+        }
+        None => {
+          let names = declared.iter().map(|param| param.name).collect::<Vec<_>>();
+          return Err(format!(
+            "Unknown parameter {:?}, expected one of: {}.",
+            name,
+            names.join(", ")
+          ));
+        }
+      };
+      (param.check)(value).map_err(|error| format!("Invalid value {:?} for parameter {:?}: {}", value, name, error))?;
+
+      params.values.insert(name.clone(), value.clone());
+    }
+
+    Ok(params)
+  }
+
+  pub fn get_str(&self, name: &str) -> &str {
+    match self.values.get(name) {
+      Some(value) => value,
+      None => panic!("parameter {:?} is not declared", name),
+    }
+  }
+
+  /// Parses the value of the parameter `name`, panics if it's invalid despite the check of the parameter.
+  pub fn get<T>(&self, name: &str) -> T
+  where
+    T: FromStr,
+    T::Err: Debug,
+  {
+    let value = self.get_str(name);
+
+    match value.parse::<T>() {
+      Ok(value) => value,
+      Err(error) => panic!("invalid value {:?} for parameter {:?}: {:?}", value, name, error),
+    }
+  }
+}
+
+/// Splits a REPL line on whitespace, keeping double-quoted parts together:
+/// `d07 color="shiny gold"` becomes `["d07", "color=shiny gold"]`.
+pub fn split_args(line: &str) -> Vec<String> {
+  let mut args = vec![];
+  let mut current = String::new();
+  let mut in_quotes = false;
+  let mut has_arg = false;
+
+  for ch in line.chars() {
+    match ch {
+      '"' => {
+        in_quotes = !in_quotes;
+        has_arg = true;
+      }
+      ch if ch.is_whitespace() && !in_quotes => {
+        if has_arg {
+          args.push(current.clone());
+          current.clear();
+          has_arg = false;
+        }
+      }
+      ch => {
+        current.push(ch);
+        has_arg = true;
+      }
+    }
+  }
+
+  if has_arg {
+    args.push(current);
+  }
+
+  args
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PARAMS: &[Param] = &[
+    Param {
+      name: "turns",
+      default: "2020",
+      doc: "Number of turns.",
+      check: parses::<usize>,
+    },
+    Param {
+      name: "color",
+      default: "shiny gold",
+      doc: "Bag color.",
+      check: any,
+    },
+  ];
+
+  #[test]
+  fn params_are_resolved() {
+    let params = Params::defaults(PARAMS);
+    assert_eq!(params.get::<usize>("turns"), 2020);
+    assert_eq!(params.get_str("color"), "shiny gold");
+
+    let params = Params::resolve(PARAMS, &[("turns".to_string(), "10".to_string())]).unwrap();
+    assert_eq!(params.get::<usize>("turns"), 10);
+
+    assert_eq!(
+      Params::resolve(PARAMS, &[("turn".to_string(), "10".to_string())]),
+      Err("Unknown parameter \"turn\", expected one of: turns, color.".to_string())
+    );
+  }
+
+  #[test]
+  fn invalid_values_are_rejected() {
+    assert_eq!(
+      Params::resolve(PARAMS, &[("turns".to_string(), "many".to_string())]),
+      Err("Invalid value \"many\" for parameter \"turns\": invalid digit found in string".to_string())
+    );
+    assert!(Params::resolve(PARAMS, &[("color".to_string(), "many".to_string())]).is_ok());
+  }
+
+  #[test]
+  fn defaults_pass_their_checks() {
+    for (name, command) in crate::commands() {
+      for param in command.params {
+        assert_eq!((param.check)(param.default), Ok(()), "{} {}", name, param.name);
+      }
+    }
+  }
+
+  #[test]
+  fn args_are_split() {
+    assert_eq!(
+      split_args(r#"  d07 sample07 color="dark olive"  depth=2 "#),
+      vec!["d07", "sample07", "color=dark olive", "depth=2"]
+    );
+    assert_eq!(split_args(r#"a="""#), vec!["a="]);
+  }
+}
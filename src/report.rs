@@ -0,0 +1,31 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Solver output that is printed as-is, instead of being quoted and escaped like a `String` answer.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Report(pub String);
+
+impl fmt::Debug for Report {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+/// Output format of reports, usually selected with a `format` parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  Text,
+  Json,
+}
+
+impl FromStr for Format {
+  type Err = String;
+
+  fn from_str(format: &str) -> Result<Format, String> {
+    match format {
+      "text" => Ok(Format::Text),
+      "json" => Ok(Format::Json),
+      _ => Err(format!("Unknown format {:?}, expected text or json.", format)),
+    }
+  }
+}
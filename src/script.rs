@@ -0,0 +1,172 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::params::Params;
+use crate::Commands;
+
+/// REPL commands that only make sense interactively.
+const DISALLOWED: [&str; 3] = ["next", "serve", "debug"];
+
+thread_local! {
+  /// Scripts being sourced, outermost first, to reject scripts sourcing themselves.
+  static SOURCING: RefCell<Vec<PathBuf>> = const { RefCell::new(vec![]) };
+}
+
+/// Executes REPL lines from the script at `path`, stopping at the first failed line.
+///
+/// Empty lines and lines starting with `#` are skipped. Scripts may source other scripts, but not cyclically.
+pub fn source(path: &str, commands: &Commands) -> Result<(), String> {
+  let script =
+    fs::read_to_string(path).map_err(|error| format!("Cannot read script {:?} due to {:?}.", path, error))?;
+
+  let canonical = Path::new(path).canonicalize().unwrap_or_else(|_| PathBuf::from(path));
+  if SOURCING.with(|sourcing| sourcing.borrow().contains(&canonical)) {
+    return Err(format!("Script {:?} is already being sourced.", path));
+  }
+
+  SOURCING.with(|sourcing| sourcing.borrow_mut().push(canonical));
+  let result = run(&script, commands);
+  SOURCING.with(|sourcing| sourcing.borrow_mut().pop());
+
+  result.map_err(|(line_number, error)| format!("{}:{}: {}", path, line_number, error))
+}
+
+/// Runs `script`, returning the failed line number together with the error.
+fn run(script: &str, commands: &Commands) -> Result<(), (usize, String)> {
+  for (idx, line) in script.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    let command = line.split_ascii_whitespace().next().unwrap_or("");
+    if DISALLOWED.contains(&command) {
+      return Err((idx + 1, format!("{:?} is not allowed in scripts.", command)));
+    }
+
+    println!(">>> {}", line);
+    crate::execute_line(line, commands).map_err(|error| (idx + 1, error))?;
+  }
+
+  Ok(())
+}
+
+/// Runs `expect <command> [input] [name=value ...] == <answer>`, failing if the solver's answer differs.
+///
+/// Answers are compared to the `Debug` representation of the result (`None` if there's no answer);
+/// string answers can be written without quotes.
+pub fn expect(line: &str, commands: &Commands) -> Result<String, String> {
+  let usage = || "Usage: expect <command> [input] [name=value ...] == <answer>.".to_string();
+
+  let (invocation, expected) = line["expect ".len()..].split_once("==").ok_or_else(usage)?;
+  let (expected, invocation) = (expected.trim(), invocation.trim());
+
+  let parsed = crate::parse_line(invocation).ok_or_else(usage)?;
+  let command = commands
+    .get(parsed.name.as_str())
+    .ok_or_else(|| format!("Unrecoginzed command: {:?}.", parsed.name))?;
+
+  let params = Params::resolve(command.params, &parsed.args)?;
+  let input = crate::read_input(&parsed.input_file)?;
+  let execution = crate::execute(command, &input, &params);
+  let answer = match execution.result {
+    Ok(Some(answer)) => format!("{:?}", answer),
+    Ok(None) => "None".to_string(),
+    Err(message) => return Err(format!("Expectation failed: {} panicked: {}", invocation, message)),
+  };
+
+  if answer == expected || answer.trim_matches('"') == expected {
+    Ok(format!("ok: {} == {} ({:?})", invocation, answer, execution.elapsed))
+  } else {
+    Err(format!(
+      "Expectation failed: {} returned {}, expected {}.",
+      invocation, answer, expected
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn expectations_are_checked() {
+    let commands = crate::commands();
+
+    assert!(expect("expect d01 == 921504", &commands).is_ok());
+    assert!(expect("expect d01_2 sample01 == 241861950", &commands).is_ok());
+    assert!(expect("expect d23 sample23 == 67384529", &commands).is_ok());
+    assert!(expect("expect d23 sample23 == \"67384529\"", &commands).is_ok());
+    assert!(expect("expect d23 sample23 moves=10 == 92658374", &commands).is_ok());
+    assert!(expect("expect d23 inputs/sample23 moves=10 == 92658374", &commands).is_ok());
+
+    assert_eq!(
+      expect("expect d01 sample01 == 1", &commands),
+      Err("Expectation failed: d01 sample01 returned 514579, expected 1.".to_string())
+    );
+    assert!(expect("expect d01 sample01", &commands).is_err());
+    assert!(expect("expect d99 == 1", &commands).is_err());
+    assert!(expect("expect d01 turns=1 == 921504", &commands).is_err());
+  }
+
+  #[test]
+  fn scripts_cannot_source_themselves() {
+    let commands = crate::commands();
+    let dir = std::env::temp_dir().join(format!("aoc2020-scripts-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let outer = dir.join("outer.aoc");
+    let inner = dir.join("inner.aoc");
+    fs::write(
+      &outer,
+      format!("expect d01 sample01 == 514579\nsource {}\n", inner.display()),
+    )
+    .unwrap();
+    fs::write(&inner, format!("source {}\n", outer.display())).unwrap();
+
+    let error = source(outer.to_str().unwrap(), &commands).unwrap_err();
+    assert!(error.ends_with(&format!(
+      "Script {:?} is already being sourced.",
+      outer.to_str().unwrap()
+    )));
+    assert!(error.starts_with(&format!("{}:2: {}:1: ", outer.display(), inner.display())));
+
+    // sourcing the same script again after it has finished is fine
+    fs::write(&inner, "expect d01 sample01 == 514579\n").unwrap();
+    fs::write(&outer, format!("source {0}\nsource {0}\n", inner.display())).unwrap();
+    assert_eq!(source(outer.to_str().unwrap(), &commands), Ok(()));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn unrecognized_lines_fail() {
+    let commands = crate::commands();
+
+    assert_eq!(
+      run("d01 sample01 bogus extra\n", &commands),
+      Err((1, "Unrecognized line \"d01 sample01 bogus extra\"".to_string()))
+    );
+  }
+
+  #[test]
+  fn scripts_stop_at_first_failure() {
+    let commands = crate::commands();
+
+    let script = "# day one\n\nexpect d01 sample01 == 514579\nexpect d01 sample01 == 1\nexpect d01 == 921504\n";
+    assert_eq!(
+      run(script, &commands),
+      Err((
+        4,
+        "Expectation failed: d01 sample01 returned 514579, expected 1.".to_string()
+      ))
+    );
+
+    assert_eq!(
+      run("next", &commands),
+      Err((1, "\"next\" is not allowed in scripts.".to_string()))
+    );
+    assert_eq!(run("expect d06 sample06 == 11\nd06_2 sample06", &commands), Ok(()));
+  }
+}
@@ -0,0 +1,430 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use crate::json::Json;
+use crate::params::Params;
+use crate::{Command, Commands};
+
+/// Requests with larger bodies are answered with 413 without reading the body.
+const MAX_BODY_LENGTH: usize = 16 * 1024 * 1024;
+
+/// Limits the request line and the headers together.
+const MAX_HEAD_LENGTH: u64 = 64 * 1024;
+
+/// Connections are served one at a time, so a slow client must not block the others for long.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Serves solvers over HTTP on `127.0.0.1:port`, handling one request at a time:
+///
+/// * `GET /commands` lists all registered commands;
+/// * `POST /solve/{command}?name=value&...` runs the command with the passed parameters on the request body,
+///   and responds with the answer, elapsed time and error (if the solver panicked) as JSON.
+pub fn serve(port: u16, commands: &Commands) -> io::Result<()> {
+  let listener = TcpListener::bind(("127.0.0.1", port))?;
+  println!("Listening on http://{}, press CTRL-C to stop.", listener.local_addr()?);
+
+  for stream in listener.incoming() {
+    if let Err(error) = stream.and_then(|stream| handle_connection(stream, commands)) {
+      println!("Failed to handle a connection: {:?}", error);
+    }
+  }
+
+  Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Request {
+  method: String,
+  path: String,
+  body: String,
+}
+
+fn handle_connection(mut stream: TcpStream, commands: &Commands) -> io::Result<()> {
+  let (status, body) = match read_request(&mut stream, Instant::now() + REQUEST_TIMEOUT) {
+    Ok(request) => respond(&request, commands),
+    Err((status, message)) => (status, Json::object(vec![("error", Json::from(message))])),
+  };
+
+  let body = body.to_string();
+  let reason = match status {
+    200 => "OK",
+    400 => "Bad Request",
+    404 => "Not Found",
+    405 => "Method Not Allowed",
+    413 => "Payload Too Large",
+    _ => "Internal Server Error",
+  };
+
+  write!(
+    stream,
+    "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    status,
+    reason,
+    body.len(),
+    body
+  )?;
+  stream.flush()
+}
+
+/// Reads a request until `deadline`, or returns the status and the message to respond with.
+fn read_request(stream: &mut TcpStream, deadline: Instant) -> Result<Request, (u16, String)> {
+  let mut stream = DeadlineReader { stream, deadline };
+  let request = read_request_head(&mut stream).map_err(|error| (400, format!("Bad request: {}", error)))?;
+  let (method, path, content_length, mut reader) = request;
+
+  if content_length > MAX_BODY_LENGTH {
+    return Err((
+      413,
+      format!(
+        "Request body of {} bytes is larger than {} bytes.",
+        content_length, MAX_BODY_LENGTH
+      ),
+    ));
+  }
+
+  let mut body = vec![0; content_length];
+  let body = reader
+    .read_exact(&mut body)
+    .and_then(|_| String::from_utf8(body).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "body is not UTF-8")))
+    .map_err(|error| (400, format!("Bad request: {}", error)))?;
+
+  Ok(Request { method, path, body })
+}
+
+/// Reads the request line and headers, returning the method, path, content length and the reader for the body.
+fn read_request_head<R: Read>(stream: R) -> io::Result<(String, String, usize, BufReader<R>)> {
+  let mut reader = BufReader::new(stream);
+  let mut head = (&mut reader).take(MAX_HEAD_LENGTH);
+  let mut read_line = |line: &mut String| {
+    head.read_line(line)?;
+    if line.ends_with('\n') {
+      Ok(())
+    } else if head.limit() == 0 {
+      let message = format!("request head is longer than {} bytes", MAX_HEAD_LENGTH);
+      Err(io::Error::new(io::ErrorKind::InvalidData, message))
+    } else {
+      Err(io::Error::new(io::ErrorKind::UnexpectedEof, "incomplete request head"))
+    }
+  };
+
+  let mut request_line = String::new();
+  read_line(&mut request_line)?;
+  let (method, path) = match request_line.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+    [method, path, _version] => (method.to_string(), path.to_string()),
+    _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed request line")),
+  };
+
+  let mut content_length = 0;
+  loop {
+    let mut header = String::new();
+    read_line(&mut header)?;
+
+    let header = header.trim_end();
+    if header.is_empty() {
+      break;
+    }
+
+    if let Some((name, value)) = header.split_once(':') {
+      if name.eq_ignore_ascii_case("content-length") {
+        content_length = value
+          .trim()
+          .parse()
+          .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid Content-Length"))?;
+      }
+    }
+  }
+
+  Ok((method, path, content_length, reader))
+}
+
+/// Reads from the stream, failing once `deadline` has passed, however slowly the bytes arrive.
+struct DeadlineReader<'a> {
+  stream: &'a mut TcpStream,
+  deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let timed_out = || io::Error::new(io::ErrorKind::TimedOut, "request took too long");
+
+    let remaining = self.deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+      return Err(timed_out());
+    }
+
+    self.stream.set_read_timeout(Some(remaining))?;
+    match self.stream.read(buf) {
+      // the socket reports its timeout as `WouldBlock` on some platforms
+      Err(error) if matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => Err(timed_out()),
+      result => result,
+    }
+  }
+}
+
+fn respond(request: &Request, commands: &Commands) -> (u16, Json) {
+  let error = |message: String| Json::object(vec![("error", Json::from(message))]);
+
+  match (request.method.as_str(), request.path.as_str()) {
+    ("GET", "/commands") => {
+      let mut names = commands.keys().cloned().collect::<Vec<_>>();
+      names.sort_unstable();
+
+      (200, Json::object(vec![("commands", Json::from(names))]))
+    }
+    ("POST", path) if path.starts_with("/solve/") => {
+      let target = &path["/solve/".len()..];
+      let (name, query) = target.split_once('?').unwrap_or((target, ""));
+
+      match commands.get(name) {
+        None => (404, error(format!("Unrecognized command: {:?}.", name))),
+        Some(command) => match Params::resolve(command.params, &parse_query(query)) {
+          Err(message) => (400, error(message)),
+          Ok(params) => solve(name, command, &request.body, &params),
+        },
+      }
+    }
+    (_, "/commands") => (405, error("Use GET for /commands.".to_string())),
+    (_, path) if path.starts_with("/solve/") => (405, error("Use POST for /solve/{command}.".to_string())),
+    (_, path) => (404, error(format!("Unknown path: {:?}.", path))),
+  }
+}
+
+fn solve(name: &str, command: &Command, input: &str, params: &Params) -> (u16, Json) {
+  let execution = crate::execute(command, input, params);
+  let elapsed_ms = Json::from(execution.elapsed.as_secs_f64() * 1000.0);
+
+  match execution.result {
+    Ok(answer) => (
+      200,
+      Json::object(vec![
+        ("command", Json::from(name)),
+        ("answer", Json::from(answer.map(|answer| format!("{:?}", answer)))),
+        ("elapsed_ms", elapsed_ms),
+        ("error", Json::Null),
+      ]),
+    ),
+    Err(message) => (
+      500,
+      Json::object(vec![
+        ("command", Json::from(name)),
+        ("answer", Json::Null),
+        ("elapsed_ms", elapsed_ms),
+        ("error", Json::from(message)),
+      ]),
+    ),
+  }
+}
+
+/// Parses `name=value&...` query parameters, decoding `+` and `%XX` escapes.
+fn parse_query(query: &str) -> Vec<(String, String)> {
+  let decode = |value: &str| {
+    let bytes = value.replace('+', " ").into_bytes();
+    let mut decoded = vec![];
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+      let escaped = bytes
+        .get(idx + 1..idx + 3)
+        .and_then(|hex| std::str::from_utf8(hex).ok())
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+      match (bytes[idx], escaped) {
+        (b'%', Some(byte)) => {
+          decoded.push(byte);
+          idx += 3;
+        }
+        (byte, _) => {
+          decoded.push(byte);
+          idx += 1;
+        }
+      }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+  };
+
+  query
+    .split('&')
+    .filter(|pair| !pair.is_empty())
+    .map(|pair| {
+      let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+      (decode(name), decode(value))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::params::{parses, Param};
+  use std::thread;
+
+  const PARAMS: &[Param] = &[Param {
+    name: "skip",
+    default: "0",
+    doc: "Lines to skip.",
+    check: parses::<usize>,
+  }];
+
+  fn test_commands() -> Commands {
+    let mut commands = Commands::new();
+    commands.insert(
+      "lines",
+      Command {
+        solver: |input: &str, params: &Params| {
+          Some(Box::new(input.lines().skip(params.get("skip")).count()) as Box<dyn std::fmt::Debug>)
+        },
+        params: PARAMS,
+      },
+    );
+    commands.insert(
+      "nothing",
+      Command {
+        solver: |_input: &str, _params: &Params| None,
+        params: &[],
+      },
+    );
+    commands.insert(
+      "broken",
+      Command {
+        solver: |_input: &str, _params: &Params| panic!("out of coffee"),
+        params: &[],
+      },
+    );
+    commands
+  }
+
+  /// Starts a server on a random port that handles `requests` connections.
+  fn start(requests: usize) -> u16 {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    thread::spawn(move || {
+      let commands = test_commands();
+      for stream in listener.incoming().take(requests) {
+        handle_connection(stream.unwrap(), &commands).unwrap();
+      }
+    });
+
+    port
+  }
+
+  fn request(port: u16, raw: &str) -> String {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+    stream.write_all(raw.as_bytes()).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+  }
+
+  fn post(port: u16, path: &str, body: &str) -> String {
+    request(
+      port,
+      &format!(
+        "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+        path,
+        body.len(),
+        body
+      ),
+    )
+  }
+
+  #[test]
+  fn commands_are_listed() {
+    let port = start(2);
+
+    let response = request(port, "GET /commands HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with(r#"{"commands":["broken","lines","nothing"]}"#));
+
+    let response = request(port, "DELETE /commands HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 405 "));
+  }
+
+  #[test]
+  fn solvers_are_called() {
+    let port = start(7);
+
+    let response = post(port, "/solve/lines", "1\n2\n3\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains(r#"{"command":"lines","answer":"3","elapsed_ms":"#));
+    assert!(response.ends_with(r#""error":null}"#));
+
+    let response = post(port, "/solve/lines?skip=%32", "1\n2\n3\n");
+    assert!(response.contains(r#""answer":"1""#));
+
+    let response = post(port, "/solve/lines?take=2", "1\n2\n3\n");
+    assert!(response.starts_with("HTTP/1.1 400 "));
+
+    let response = post(port, "/solve/lines?skip=two", "1\n2\n3\n");
+    assert!(response.starts_with("HTTP/1.1 400 "));
+    assert!(
+      response.ends_with(r#"{"error":"Invalid value \"two\" for parameter \"skip\": invalid digit found in string"}"#)
+    );
+
+    let response = post(port, "/solve/nothing", "");
+    assert!(response.contains(r#""answer":null"#));
+
+    let response = post(port, "/solve/broken", "");
+    assert!(response.starts_with("HTTP/1.1 500 "));
+    assert!(response.ends_with(r#""error":"out of coffee"}"#));
+
+    let response = post(port, "/solve/d99", "");
+    assert!(response.starts_with("HTTP/1.1 404 "));
+    assert!(response.ends_with(r#"{"error":"Unrecognized command: \"d99\"."}"#));
+  }
+
+  #[test]
+  fn large_bodies_are_rejected() {
+    let port = start(1);
+
+    let response = request(
+      port,
+      "POST /solve/lines HTTP/1.1\r\nContent-Length: 18446744073709551615\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+    assert!(
+      response.ends_with(r#"{"error":"Request body of 18446744073709551615 bytes is larger than 16777216 bytes."}"#)
+    );
+  }
+
+  #[test]
+  fn large_heads_are_rejected() {
+    let port = start(1);
+
+    // exactly at the limit, so that the server reads all of it before responding
+    let mut head = "GET /commands HTTP/1.1\r\nX-Padding: ".to_string();
+    head += &"a".repeat(MAX_HEAD_LENGTH as usize - head.len());
+    let response = request(port, &head);
+    assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    assert!(response.ends_with(r#"{"error":"Bad request: request head is longer than 65536 bytes"}"#));
+  }
+
+  #[test]
+  fn slow_requests_time_out() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // a byte every 20ms never stops a single read, but the whole request takes too long
+    let client = thread::spawn(move || {
+      let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+      for byte in "GET /commands HTTP/1.1\r\n".bytes().cycle().take(100) {
+        if stream.write_all(&[byte]).is_err() {
+          break;
+        }
+        thread::sleep(Duration::from_millis(20));
+      }
+    });
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let started = Instant::now();
+    let result = read_request(&mut stream, started + Duration::from_millis(200));
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(result, Err((400, "Bad request: request took too long".to_string())));
+
+    drop(stream);
+    client.join().unwrap();
+  }
+}